@@ -3,4 +3,47 @@ use anchor_lang::prelude::*;
 #[constant]
 pub const SOL_USD_FEED_ID: &str = "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
 pub const USDC_USD_FEED_ID: &str = "0xeaa020c61cc479712813461ce153894a96a6c00b21ed0cfc2798d1f9a9e9c94a";
-pub const MAX_AGE: u64 = 30;
\ No newline at end of file
+pub const MAX_AGE: u64 = 30;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+pub const USDC_DECIMALS: u32 = 6;
+pub const SOL_DECIMALS: u32 = 9;
+pub const SECONDS_PER_YEAR: u64 = 365 * 86_400;
+
+// Default loan terms in basis points, used by init_config
+pub const DEFAULT_COLLATERAL_RATIO_BPS: u16 = 15_000;
+pub const DEFAULT_LIQUIDATION_THRESHOLD_BPS: u16 = 11_000;
+pub const DEFAULT_LENDER_FEE_BPS: u16 = 400;
+pub const DEFAULT_PROTOCOL_FEE_BPS: u16 = 100;
+pub const DEFAULT_CLAIM_FEE_BPS: u16 = 1_000;
+pub const DEFAULT_LIQUIDATION_FEE_BPS: u16 = 182;
+pub const DEFAULT_GRACE_PERIOD_DAYS: u16 = 3;
+pub const DEFAULT_LATE_PENALTY_BPS_PER_DAY: u16 = 50;
+
+// Sanity bounds enforced by update_config
+pub const MAX_COLLATERAL_RATIO_BPS: u16 = 50_000;
+pub const MAX_LENDER_FEE_BPS: u16 = 5_000;
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 1_000;
+pub const MAX_CLAIM_FEE_BPS: u16 = 5_000;
+pub const MAX_LIQUIDATION_FEE_BPS: u16 = 2_000;
+pub const MAX_COLLATERAL_HAIRCUT_BPS: u16 = 5_000;
+pub const MAX_APR_BPS: u16 = 10_000;
+pub const MAX_GRACE_PERIOD_DAYS: u16 = 30;
+pub const MAX_LATE_PENALTY_BPS_PER_DAY: u16 = 500;
+pub const MAX_FORGIVENESS_FEE: u64 = 100_000_000; // 100 USDC
+
+// Number of loan requests stored in one registry page, must match #[max_len] on LoanRegistryPageState
+pub const LOAN_REGISTRY_PAGE_SIZE: usize = 20;
+
+// Number of loan offers stored in one offer registry page, must match #[max_len] on OfferRegistryPageState
+pub const OFFER_REGISTRY_PAGE_SIZE: usize = 20;
+
+// Number of lenders a private loan request can name, must match #[max_len] on LoanRequestState
+pub const MAX_ALLOWED_LENDERS: usize = 5;
+
+// Number of lenders that can share a syndicated loan, bounded by the accounts a settlement can carry
+pub const MAX_TRANCHES: u16 = 5;
+
+// Virtual shares and USDC added to the lending pool when pricing shares, so that the first depositor can't
+// inflate the share price with a donation and round later deposits down to nothing
+pub const POOL_VIRTUAL_SHARES: u64 = 1_000_000;
+pub const POOL_VIRTUAL_ASSETS: u64 = 1_000_000; // 1 USDC
@@ -0,0 +1,68 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+use crate::helpers::{calculate_grace_deadline, check_deadline_is_not_expired, get_loan_lender};
+use crate::state::{LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct AcceptLoanExtension<'info> {
+
+    //the lender recorded on the loan, or the holder of its position NFT, accepts the extension
+    pub lender: Signer<'info>,
+
+    //Loan Request, a syndicated loan has no single lender to accept an extension
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump,
+        constraint = loan_request.tranche_count == 0 @ ErrorCode::SyndicatedLoanNotSupported,
+        constraint = get_loan_lender(&loan_request, position_token_account.as_deref().map(|account| &**account)) == Some(lender.key()) @ ErrorCode::NotRightLender
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //token account holding the position NFT of the loan, only for loans funded with fund_loan
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+}
+
+impl<'info> AcceptLoanExtension<'info> {
+
+    /// Accepts the pending extension. The lender passes the duration and fee it agrees to,
+    /// so a proposal replaced before this transaction lands is rejected. Syndicated loans can't be extended.
+    pub fn accept_loan_extension(&mut self, duration_days: u64, fee: u64) -> Result<()> {
+
+        require!(self.loan_request.status == LoanStatus::Funded, ErrorCode::NotFunded);
+        check_deadline_is_not_expired(calculate_grace_deadline(&self.loan_request)?)?;
+
+        let extension = self.loan_request.pending_extension.ok_or(ErrorCode::NoPendingExtension)?;
+        require!(
+            extension.duration_days == duration_days && extension.fee == fee,
+            ErrorCode::InvalidExtension
+        );
+
+        //move the deadline back by the extra days, keeping it counted from funding
+        let extra_seconds = extension.duration_days
+            .checked_sub(self.loan_request.duration_days)
+            .and_then(|days| days.checked_mul(86400))
+            .ok_or(ErrorCode::Overflow)? as i64;
+        let repayment_time = self.loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+        let new_repayment_time = repayment_time
+            .checked_add(extra_seconds)
+            .ok_or(ErrorCode::Overflow)?;
+
+        //record the extension on the loan
+        self.loan_request.repayment_time = Some(new_repayment_time);
+        self.loan_request.duration_days = extension.duration_days;
+        self.loan_request.extension_fees = self.loan_request.extension_fees
+            .checked_add(extension.fee)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.loan_request.extension_count = self.loan_request.extension_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.loan_request.pending_extension = None;
+
+        Ok(())
+    }
+}
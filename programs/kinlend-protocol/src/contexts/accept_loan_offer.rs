@@ -0,0 +1,261 @@
+use anchor_lang::{prelude::*, system_program};
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::constants::SOL_DECIMALS;
+use crate::errors::ErrorCode;
+use crate::helpers::{
+    add_to_loan_registry, check_usdc_mint_address, get_sol_price, mint_position_nft, start_loan, transfer_loan_funds,
+};
+use crate::math::required_collateral;
+use crate::state::{
+    CollateralVaultState, ConfigState, LoanOfferState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus,
+};
+
+#[derive(Accounts)]
+#[instruction(offer_id: u64, loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64, page_index: u64)]
+pub struct AcceptLoanOffer<'info> {
+    // Borrower taking the offer, pays for the loan accounts and locks the collateral
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    // Offer being taken
+    #[account(
+        mut,
+        seeds = [b"loan_offer", loan_offer.lender.as_ref(), &offer_id.to_le_bytes()],
+        bump = loan_offer.bump
+    )]
+    pub loan_offer: Box<Account<'info, LoanOfferState>>,
+
+    // Lender of the offer, receives the position NFT of the loan
+    #[account(address = loan_offer.lender @ ErrorCode::NotRightLender)]
+    pub lender: SystemAccount<'info>,
+
+    // Escrow holding the USDC of the offer
+    #[account(
+        mut,
+        seeds = [b"offer_escrow", loan_offer.key().as_ref()],
+        bump
+    )]
+    pub offer_escrow: Box<Account<'info, TokenAccount>>,
+
+    // Creating LoanRequestState Account, funded by the offer's lender
+    #[account(
+        init,
+        space = 8 + LoanRequestState::INIT_SPACE,
+        payer = borrower,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    // Creating Vault account
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + CollateralVaultState::INIT_SPACE,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    // Borrower's USDC associated token account receiving the loan
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = borrower
+    )]
+    pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    // Position NFT of the loan minted to the offer's lender, the first position of the new loan
+    #[account(
+        init,
+        payer = borrower,
+        mint::decimals = 0,
+        mint::authority = loan_request,
+        seeds = [b"position_mint", loan_request.key().as_ref(), &0u16.to_le_bytes()],
+        bump
+    )]
+    pub position_mint: Box<Account<'info, Mint>>,
+
+    // Lender's associated token account receiving the position NFT
+    #[account(
+        init,
+        payer = borrower,
+        associated_token::mint = position_mint,
+        associated_token::authority = lender
+    )]
+    pub lender_position_token_account: Box<Account<'info, TokenAccount>>,
+
+    // Root registry Account
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    // Registry page chosen by the borrower, must have a free slot
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &page_index.to_le_bytes()],
+        bump = loan_registry_page.bump
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    // Config Account, its current terms are snapshotted into the loan
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    // Pyth pull-oracle price update for SOL/USD, used to price the collateral on-chain
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AcceptLoanOffer<'info> {
+    /// Takes a loan of `loan_amount` from the offer: the SOL collateral is locked,
+    /// the loan is started in the offer lender's name and funded from the offer escrow.
+    /// The offer's lender receives the position NFT of the loan, as with `fund_loan`.
+    pub fn accept_loan_offer(
+        &mut self,
+        loan_id: u64,
+        loan_amount: u64,
+        collateral: u64,
+        duration_days: u64,
+        page_index: u64,
+        bumps: AcceptLoanOfferBumps,
+    ) -> Result<()> {
+        // 1. The loan must fit within the offer.
+        self.verify_offer_terms(loan_amount, duration_days)?;
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+
+        // 2. Collateral is priced at the offer's ratio, which is never below the protocol ratio.
+        let collateral_ratio_bps = self.loan_offer.min_collateral_ratio_bps;
+        let sol_price = get_sol_price(&self.price_update)?;
+        let required = required_collateral(loan_amount, collateral_ratio_bps as u64, SOL_DECIMALS, &sol_price)?;
+        require!(collateral >= required, ErrorCode::InsuffientCollateral);
+
+        // 3. Create the loan and start it in the lender's name.
+        self.initialize_loan_request(loan_id, loan_amount, collateral, duration_days, page_index, collateral_ratio_bps)?;
+        start_loan(&mut self.loan_request, self.loan_offer.lender)?;
+        self.collateral_vault.set_inner(CollateralVaultState {
+            bump: bumps.collateral_vault,
+        });
+
+        // 4. Lock the collateral and list the loan.
+        self.transfer_collateral_to_vault(collateral)?;
+        let loan_request_key = self.loan_request.key();
+        add_to_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)?;
+
+        // 5. Fund the borrower from the offer escrow.
+        self.transfer_from_offer(loan_amount)?;
+        self.loan_offer.available_amount = self.loan_offer.available_amount
+            .checked_sub(loan_amount)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        // 6. Mint the position NFT to the lender, whoever holds it is paid from now on.
+        mint_position_nft(
+            &self.token_program.to_account_info(),
+            &self.position_mint.to_account_info(),
+            &self.lender_position_token_account.to_account_info(),
+            &mut self.loan_request,
+            bumps.loan_request,
+        )
+    }
+
+    /// Verifies the amount and duration chosen by the borrower against the offer.
+    fn verify_offer_terms(&self, loan_amount: u64, duration_days: u64) -> Result<()> {
+        require!(loan_amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            loan_amount <= self.loan_offer.max_amount
+                && loan_amount <= self.loan_offer.available_amount
+                && duration_days > 0
+                && duration_days <= self.loan_offer.max_duration_days,
+            ErrorCode::InvalidLoanOffer
+        );
+        Ok(())
+    }
+
+    /// Initializes the LoanRequest state, the lender's collateral ratio replaces the protocol one in its terms.
+    fn initialize_loan_request(
+        &mut self,
+        loan_id: u64,
+        loan_amount: u64,
+        collateral: u64,
+        duration_days: u64,
+        registry_page: u64,
+        collateral_ratio_bps: u16,
+    ) -> Result<()> {
+        let mut terms = self.config.terms;
+        terms.collateral_ratio_bps = collateral_ratio_bps;
+
+        self.loan_request.set_inner(LoanRequestState {
+            loan_id,
+            loan_amount,
+            outstanding_principal: loan_amount,
+            apr_bps: None,
+            collateral, // stored in lamports
+            collateral_mint: None,
+            collateral_haircut_bps: 0,
+            duration_days,
+            borrower: self.borrower.key(),
+            lender: None,
+            allowed_lenders: Vec::new(),
+            repayment_time: None,
+            terms,
+            status: LoanStatus::Requested,
+            registry_page,
+            pending_extension: None,
+            extension_count: 0,
+            extension_fees: 0,
+            subscribed_amount: 0,
+            tranche_count: 0,
+            position_mint: None,
+            position_count: 0,
+        });
+        Ok(())
+    }
+
+    /// Transfers the collateral (in lamports) from the borrower's wallet to the collateral vault.
+    fn transfer_collateral_to_vault(&self, collateral: u64) -> Result<()> {
+        let cpi_accounts = system_program::Transfer {
+            from: self.borrower.to_account_info(),
+            to: self.collateral_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+        system_program::transfer(cpi_ctx, collateral)
+    }
+
+    /// Transfers the loan amount from the offer escrow to the borrower, signed by the offer PDA.
+    fn transfer_from_offer(&self, amount: u64) -> Result<()> {
+        let offer_id_bytes = self.loan_offer.offer_id.to_le_bytes();
+        let offer_seeds: &[&[u8]] = &[
+            b"loan_offer",
+            self.loan_offer.lender.as_ref(),
+            &offer_id_bytes,
+            &[self.loan_offer.bump],
+        ];
+
+        transfer_loan_funds(
+            &self.token_program.to_account_info(),
+            &self.offer_escrow.to_account_info(),
+            &self.borrower_usdc_account.to_account_info(),
+            &self.loan_offer.to_account_info(),
+            &[offer_seeds],
+            amount,
+        )
+    }
+}
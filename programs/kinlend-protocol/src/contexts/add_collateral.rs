@@ -0,0 +1,118 @@
+use anchor_lang::{prelude::*, system_program::{transfer as system_transfer, Transfer as SystemTransfer}};
+use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
+
+use crate::helpers::check_deadline_is_not_expired;
+use crate::state::{CollateralVaultState, LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct AddCollateral<'info> {
+
+    //only the borrower can top up his/her own loan
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    //Loan Request
+    #[account(
+        mut,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //Collateral Vault, receives SOL collateral
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    //token vault holding SPL collateral, only for loans backed by a whitelisted mint
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account the SPL collateral is taken from
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddCollateral<'info> {
+
+    //top up the collateral of a requested or funded loan
+    pub fn add_collateral(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        self.check_loan_is_open()?;
+
+        if self.loan_request.collateral_mint.is_some() {
+            self.transfer_tokens_to_vault(amount)?;
+        } else {
+            self.transfer_lamports_to_vault(amount)?;
+        }
+
+        self.loan_request.collateral = self.loan_request.collateral
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+
+
+    //collateral can only be added before the loan is settled and before its deadline
+    fn check_loan_is_open(&self) -> Result<()> {
+        match self.loan_request.status {
+            LoanStatus::Requested => Ok(()),
+            LoanStatus::Funded => {
+                let repayment_time = self.loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+                check_deadline_is_not_expired(repayment_time)
+            }
+            _ => Err(ErrorCode::LoanAlreadyClosed.into()),
+        }
+    }
+
+
+    //move lamports from the borrower's wallet into the collateral vault
+    fn transfer_lamports_to_vault(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = SystemTransfer {
+            from: self.borrower.to_account_info(),
+            to: self.collateral_vault.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+        system_transfer(cpi_ctx, amount)
+    }
+
+
+    //move tokens from the borrower's token account into the collateral token vault
+    fn transfer_tokens_to_vault(&self, amount: u64) -> Result<()> {
+        let (Some(token_vault), Some(borrower_account), Some(token_program)) = (
+            &self.collateral_token_vault,
+            &self.borrower_collateral_account,
+            &self.token_program,
+        ) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+
+        let cpi_accounts = Transfer {
+            from: borrower_account.to_account_info(),
+            to: token_vault.to_account_info(),
+            authority: self.borrower.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, amount)
+    }
+}
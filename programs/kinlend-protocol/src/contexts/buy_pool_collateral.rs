@@ -0,0 +1,75 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::constants::SOL_DECIMALS;
+use crate::helpers::{get_sol_price, transfer_lamports};
+use crate::math::collateral_value_usd;
+use crate::state::LendingPoolState;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct BuyPoolCollateral<'info> {
+    // Buyer paying USDC for the SOL seized by the pool
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    // Pool vault receiving the USDC
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    // Buyer's USDC token account paying for the SOL
+    #[account(
+        mut,
+        constraint = buyer_usdc_account.mint == pool_usdc_vault.mint,
+        constraint = buyer_usdc_account.owner == buyer.key()
+    )]
+    pub buyer_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    // Pyth pull-oracle price update for SOL/USD, prices the sale
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> BuyPoolCollateral<'info> {
+    /// Sells `lamports` of the SOL seized by the pool at the oracle price, turning it back into USDC
+    /// depositors can redeem. The pool value is unchanged by the sale.
+    pub fn buy_pool_collateral(&mut self, lamports: u64) -> Result<()> {
+        require!(
+            lamports > 0 && lamports <= self.lending_pool.seized_collateral,
+            ErrorCode::InvalidAmount
+        );
+
+        let sol_price = get_sol_price(&self.price_update)?;
+        let price = collateral_value_usd(lamports, SOL_DECIMALS, &sol_price)?;
+        require!(price > 0, ErrorCode::InvalidAmount);
+
+        let cpi_accounts = Transfer {
+            from: self.buyer_usdc_account.to_account_info(),
+            to: self.pool_usdc_vault.to_account_info(),
+            authority: self.buyer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, price)?;
+
+        // The lending pool is owned by this program, so lamports are moved directly.
+        transfer_lamports(&self.lending_pool.to_account_info(), &self.buyer.to_account_info(), lamports)?;
+        self.lending_pool.seized_collateral = self.lending_pool.seized_collateral
+            .checked_sub(lamports)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+}
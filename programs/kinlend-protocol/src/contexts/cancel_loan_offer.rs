@@ -0,0 +1,92 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{close_account, CloseAccount, Token, TokenAccount};
+
+use crate::helpers::{remove_from_offer_registry, transfer_loan_funds};
+use crate::state::{LoanOfferState, OfferRegistryPageState, OfferRegistryState};
+
+#[derive(Accounts)]
+#[instruction(offer_id: u64)]
+pub struct CancelLoanOffer<'info> {
+    // Only the lender who posted the offer can withdraw it
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Offer being withdrawn, its rent goes back to the lender
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        seeds = [b"loan_offer", lender.key().as_ref(), &offer_id.to_le_bytes()],
+        bump = loan_offer.bump
+    )]
+    pub loan_offer: Box<Account<'info, LoanOfferState>>,
+
+    // Escrow holding the USDC left in the offer
+    #[account(
+        mut,
+        seeds = [b"offer_escrow", loan_offer.key().as_ref()],
+        bump
+    )]
+    pub offer_escrow: Box<Account<'info, TokenAccount>>,
+
+    // Lender's USDC token account receiving what is left in the escrow
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == offer_escrow.mint,
+        constraint = lender_usdc_account.owner == lender.key()
+    )]
+    pub lender_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"offer_registry"],
+        bump
+    )]
+    pub offer_registry: Box<Account<'info, OfferRegistryState>>,
+
+    // Offer registry page listing this offer
+    #[account(
+        mut,
+        seeds = [b"offer_registry_page", &loan_offer.registry_page.to_le_bytes()],
+        bump = offer_registry_page.bump
+    )]
+    pub offer_registry_page: Box<Account<'info, OfferRegistryPageState>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> CancelLoanOffer<'info> {
+    /// Withdraws the offer: the USDC left in the escrow goes back to the lender and the escrow is closed.
+    /// Loans already taken from the offer are not affected.
+    pub fn cancel_loan_offer(&mut self) -> Result<()> {
+        let offer_id_bytes = self.loan_offer.offer_id.to_le_bytes();
+        let offer_seeds: &[&[u8]] = &[
+            b"loan_offer",
+            self.loan_offer.lender.as_ref(),
+            &offer_id_bytes,
+            &[self.loan_offer.bump],
+        ];
+        let signer_seeds = &[offer_seeds];
+
+        // The whole balance is refunded, including anything sent to the escrow directly.
+        transfer_loan_funds(
+            &self.token_program.to_account_info(),
+            &self.offer_escrow.to_account_info(),
+            &self.lender_usdc_account.to_account_info(),
+            &self.loan_offer.to_account_info(),
+            signer_seeds,
+            self.offer_escrow.amount,
+        )?;
+
+        let cpi_accounts = CloseAccount {
+            account: self.offer_escrow.to_account_info(),
+            destination: self.lender.to_account_info(),
+            authority: self.loan_offer.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+        close_account(cpi_ctx)?;
+
+        let loan_offer_key = self.loan_offer.key();
+        remove_from_offer_registry(&mut self.offer_registry, &mut self.offer_registry_page, loan_offer_key)
+    }
+}
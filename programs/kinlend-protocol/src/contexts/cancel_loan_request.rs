@@ -1,6 +1,11 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
 
-use crate::state::{CollateralVaultState, LoanRegistryState, LoanRequestState};
+use crate::helpers::{
+    calculate_tranche_shares, close_tranche_escrow, load_tranches, release_token_collateral, remove_from_loan_registry,
+    settle_tranches, transfer_from_tranche_escrow, transition_loan_status,
+};
+use crate::state::{CollateralVaultState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus};
 
 use crate::errors::ErrorCode;
 
@@ -39,6 +44,41 @@ pub struct CancelLoanRequest<'info> {
     )]
     pub loan_registry: Box<Account<'info, LoanRegistryState>>,
 
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    //token vault holding SPL collateral, only for loans backed by a whitelisted mint
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account receiving the SPL collateral back
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //escrow holding the tranches of a partly subscribed syndicated loan, refunded to their lenders
+    #[account(
+        mut,
+        seeds = [b"tranche_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    pub tranche_escrow: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
     //program
     pub system_program: Program<'info, System>
 }
@@ -46,44 +86,102 @@ pub struct CancelLoanRequest<'info> {
 impl<'info> CancelLoanRequest<'info> {
 
     //cancel loan request
-    pub fn cancel_loan_request(&mut self) -> Result<()> {
+    pub fn cancel_loan_request(&mut self, remaining_accounts: &[AccountInfo<'info>], loan_request_bump: u8) -> Result<()> {
 
-        //check loan funded or not by checking whether lender is assgined to given loan request
-        self.check_loan_funded()?;
+        //only a loan request which is not funded yet can be cancelled
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Cancelled)?;
+        self.return_token_collateral()?;
+        self.refund_tranches(remaining_accounts, loan_request_bump)?;
         self.remove_from_loan_registry()?;
 
         Ok(())
     }
 
-    //checking whether loan is funded or not
-    fn check_loan_funded(&mut self) -> Result<()> {
 
-        if self.loan_request.lender.is_some() {
-            return Err(ErrorCode::AlreadyFunded.into());
+    // SOL collateral goes back with the vault on close, SPL collateral is returned from the token vault
+    fn return_token_collateral(&self) -> Result<()> {
+        if self.loan_request.collateral_mint.is_none() {
+            return Ok(());
         }
 
-        Ok(())
+        let (Some(token_vault), Some(borrower_account), Some(token_program)) = (
+            &self.collateral_token_vault,
+            &self.borrower_collateral_account,
+            &self.token_program,
+        ) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+
+        release_token_collateral(
+            &token_program.to_account_info(),
+            &token_vault.to_account_info(),
+            &borrower_account.to_account_info(),
+            &self.borrower.to_account_info(),
+            &self.collateral_vault.to_account_info(),
+            self.loan_request.key(),
+            self.collateral_vault.bump,
+            token_vault.amount,
+        )
+    }
+
+
+    // tranches already put up by lenders go back to them and the escrow is closed,
+    // the lender positions come in (position, lender USDC account) pairs in the remaining accounts
+    fn refund_tranches(&self, remaining_accounts: &[AccountInfo<'info>], loan_request_bump: u8) -> Result<()> {
+        if self.loan_request.tranche_count == 0 {
+            return Ok(());
+        }
+
+        let (Some(tranche_escrow), Some(token_program)) = (&self.tranche_escrow, &self.token_program) else {
+            return Err(ErrorCode::InvalidTrancheAccounts.into());
+        };
+
+        let loan_request_key = self.loan_request.key();
+        let mut tranches = load_tranches(
+            loan_request_key,
+            self.loan_request.tranche_count,
+            remaining_accounts,
+            Some(tranche_escrow.mint),
+        )?;
+
+        let token_program = token_program.to_account_info();
+        let escrow_info = tranche_escrow.to_account_info();
+        let loan_request = self.loan_request.to_account_info();
+        let borrower = self.loan_request.borrower;
+        let loan_id = self.loan_request.loan_id;
+
+        // the whole escrow is shared out, so each lender gets at least their tranche back
+        let refunds = calculate_tranche_shares(&tranches, tranche_escrow.amount)?;
+        for (tranche, refund) in tranches.iter().zip(refunds) {
+            transfer_from_tranche_escrow(
+                &token_program,
+                &escrow_info,
+                &tranche.destination,
+                &loan_request,
+                borrower,
+                loan_id,
+                loan_request_bump,
+                refund,
+            )?;
+        }
+        settle_tranches(&mut tranches)?;
+
+        close_tranche_escrow(
+            &token_program,
+            &escrow_info,
+            &self.borrower.to_account_info(),
+            &loan_request,
+            borrower,
+            loan_id,
+            loan_request_bump,
+        )
     }
 
 
-    // remove the loan request from the loan registry
+    // remove the loan request from its loan registry page
     fn remove_from_loan_registry(&mut self) -> Result<()> {
         let loan_request_key = self.loan_request.key();
-        
-        // Find the index of the loan request in the registry
-        let position = self.loan_registry.loan_requests.iter()
-            .position(|&pubkey| pubkey == loan_request_key)
-            .ok_or(ErrorCode::NotFoundInRegistry)?;
-        
-        // Remove the loan request from the registry
-        self.loan_registry.loan_requests.remove(position);
-        
-        // Decrement the total loans counter
-        self.loan_registry.total_loans = self.loan_registry.total_loans
-            .checked_sub(1)
-            .ok_or(ErrorCode::CalculationError)?;
-            
-        Ok(())
+        remove_from_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
     }
 
 
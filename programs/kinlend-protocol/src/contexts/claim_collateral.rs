@@ -1,8 +1,16 @@
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{transfer, Transfer};
-use crate::helpers::check_deadline_is_expired;
+use anchor_spl::token::{Token, TokenAccount};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::helpers::{
+    calculate_grace_deadline, calculate_lender_claim, calculate_outstanding_lender_due, check_deadline_is_expired,
+    get_collateral_price, get_loan_lender, get_token_vault_collateral, get_vault_collateral, load_tranches, pay_seized_collateral,
+    remove_from_loan_registry, settle_tranches, transition_loan_status, SeizedCollateral, Tranche,
+};
+use crate::math::split_seized_collateral;
 use crate::state::{
-    CollateralVaultState, LoanRegistryState, LoanRequestState, ProtocolVaultState,
+    CollateralMintState, CollateralVaultState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus,
+    ProtocolVaultState,
 };
 use crate::errors::ErrorCode;
 
@@ -13,6 +21,13 @@ pub struct ClaimCollateral<'info> {
     #[account(mut)]
     pub lender: Signer<'info>,
 
+    /// Borrower of the loan, receives any collateral left after the lender and the protocol are paid.
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
     /// Loan Request account (PDA) containing loan details.
     /// Derived using: [b"loan_request", loan_request.borrower.as_ref(), &loan_request.loan_id.to_le_bytes()]
     #[account(
@@ -22,14 +37,15 @@ pub struct ClaimCollateral<'info> {
     )]
     pub loan_request: Box<Account<'info, LoanRequestState>>,
 
-    /// Collateral Vault (PDA) that holds the collateral (in SOL lamports).
-    /// When closed, any remaining lamports are sent to the lender.
+    /// Collateral Vault (PDA) that holds SOL collateral and owns the token vault of SPL collateral.
+    /// When closed, the surplus collateral and the rent deposit are returned to the borrower.
     #[account(
         mut,
+        close = borrower,
         seeds = [b"collateral_vault", loan_request.key().as_ref()],
         bump = collateral_vault.bump
     )]
-    pub collateral_vault: Account<'info, CollateralVaultState>,
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
 
     /// Protocol Vault account (PDA) that collects fees.
     #[account(
@@ -47,6 +63,61 @@ pub struct ClaimCollateral<'info> {
     )]
     pub loan_registry: Box<Account<'info, LoanRegistryState>>,
 
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    /// Pyth pull-oracle price update for the collateral feed, used to size the lender's share.
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    /// Whitelisted mint config pricing SPL collateral. Only for loans backed by a whitelisted mint.
+    pub collateral_mint_config: Option<Box<Account<'info, CollateralMintState>>>,
+
+    /// Token vault holding SPL collateral.
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Lender's token account receiving the lender's share of SPL collateral.
+    /// A syndicated loan pays its tranche lenders from the remaining accounts instead.
+    #[account(
+        mut,
+        constraint = Some(lender_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = lender_collateral_account.owner == lender.key()
+    )]
+    pub lender_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Token account holding the position NFT of the loan, only for loans funded with `fund_loan`.
+    /// Its owner is the lender owed the loan.
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Borrower's token account receiving the surplus SPL collateral.
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Protocol token account collecting the claim fee in SPL collateral.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault_token", protocol_collateral_vault.mint.as_ref()],
+        bump,
+        constraint = Some(protocol_collateral_vault.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub protocol_collateral_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
     /// System Program for lamport transfers.
     pub system_program: Program<'info, System>,
 }
@@ -54,12 +125,30 @@ pub struct ClaimCollateral<'info> {
 impl<'info> ClaimCollateral<'info> {
     /// Main function to claim collateral.
     /// It ensures the loan is defaulted, obtains the collateral amount, and transfers:
-    /// - 10% as a fee to the protocol vault,
-    /// - 90% to the lender.
-    pub fn claim_collateral(&mut self) -> Result<()> {
+    /// - collateral worth the outstanding principal plus lender fee or interest to the lender,
+    /// - the claim fee (10% of the lender's share by default) to the protocol vault,
+    /// - any surplus back to the borrower when the vault is closed.
+    ///
+    /// A syndicated loan can be claimed by any of its tranche lenders, passed in the remaining accounts
+    /// as (lender position, destination) pairs, and the lender's share is split pro rata across them.
+    pub fn claim_collateral(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
         self.ensure_loan_defaulted()?;
+        let mut tranches = self.load_tranches(remaining_accounts)?;
         let collateral_amount = self.get_collateral()?;
-        self.transfer_collateral(collateral_amount)?;
+        let (price, _haircut_price, decimals) = get_collateral_price(
+            &self.loan_request,
+            self.collateral_mint_config.as_deref().map(|config| &**config),
+            &self.price_update,
+        )?;
+        let lender_due = calculate_outstanding_lender_due(&self.loan_request)?;
+        let lender_claim = calculate_lender_claim(lender_due, decimals, &price)?;
+        let (lender_amount, fee, _borrower_amount) = split_seized_collateral(
+            collateral_amount,
+            lender_claim,
+            self.loan_request.terms.claim_fee_bps as u64,
+        )?;
+        pay_seized_collateral(self.seized_collateral(), lender_amount, fee, &tranches)?;
+        settle_tranches(&mut tranches)?;
         self.remove_from_loan_registry()?;
 
         Ok(())
@@ -67,107 +156,81 @@ impl<'info> ClaimCollateral<'info> {
 
     /// Verifies that:
     /// The loan is funded by the caller (lender).
-    /// The loan is defaulted (i.e. current time is past the computed deadline).
+    /// The loan is defaulted (i.e. current time is past the deadline and its grace period).
     ///
     /// Deadline Calculation: 
-    /// If your state stored the computed deadline, you would simply compare it.  
-    /// Here we assume the state stores only the funding time and duration.
-    pub fn ensure_loan_defaulted(&self) -> Result<()> {
-        // Check that the loan request is funded by the calling lender.
-        let funded_lender = self.loan_request.lender;
-        require!(
-            funded_lender == Some(self.lender.key()),
-            ErrorCode::UnauthorizedLender
-        );
+    /// The loan stores its repayment deadline, the borrower can still repay
+    /// during the grace period after it, so claiming waits until that window closes.
+    pub fn ensure_loan_defaulted(&mut self) -> Result<()> {
+        // Only a funded loan can default.
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Defaulted)?;
+
+        // Check that the loan request is funded by the calling lender or that it holds the position NFT,
+        // tranche lenders of a syndicated loan are checked against their positions.
+        if self.loan_request.tranche_count == 0 {
+            let funded_lender = get_loan_lender(
+                &self.loan_request,
+                self.position_token_account.as_deref().map(|account| &**account),
+            );
+            require!(
+                funded_lender == Some(self.lender.key()),
+                ErrorCode::UnauthorizedLender
+            );
+        }
 
-        let repayment_time = self.loan_request.repayment_time.unwrap();
+        let grace_deadline = calculate_grace_deadline(&self.loan_request)?;
 
-        check_deadline_is_expired(repayment_time)?;
+        check_deadline_is_expired(grace_deadline)?;
 
         Ok(())
     }
 
-    /// Retrieves the total lamport balance from the collateral vault.
-    pub fn get_collateral(&self) -> Result<u64> {
-        let vault_lamports = self.collateral_vault.to_account_info().lamports();
-        require!(vault_lamports > 0, ErrorCode::NoCollateral);
-        Ok(vault_lamports)
+    /// Loads the positions of a syndicated loan, the caller must hold one of them.
+    /// SOL goes to the tranche lenders' wallets, SPL collateral to their token accounts of the collateral mint.
+    fn load_tranches(&self, remaining_accounts: &[AccountInfo<'info>]) -> Result<Vec<Tranche<'info>>> {
+        let tranches = load_tranches(
+            self.loan_request.key(),
+            self.loan_request.tranche_count,
+            remaining_accounts,
+            self.loan_request.collateral_mint,
+        )?;
+        require!(
+            tranches.is_empty() || tranches.iter().any(|tranche| tranche.position.lender == self.lender.key()),
+            ErrorCode::UnauthorizedLender
+        );
+        Ok(tranches)
     }
 
-    /// Transfers collateral from the collateral vault:
-    /// - 90% goes to the lender,
-    /// - 10% goes to the protocol vault.
-    /// 
-    /// The function calculates the fee and lender amount, then uses CPI to the system program
-    /// with the collateral vault PDA signing the transfer.
-    pub fn transfer_collateral(&self, total_amount: u64) -> Result<()> {
-        // Calculate fee: 10% of the total collateral.
-        let fee = total_amount
-            .checked_div(10)
-            .ok_or(ErrorCode::Overflow)?;
-        // Calculate lender's share: remaining 90%.
-        let lender_amount = total_amount
-            .checked_sub(fee)
-            .ok_or(ErrorCode::Overflow)?;
-
-        // The collateral vault PDA's seeds.
-        let loan_request_key = self.loan_request.key();
-        let collateral_seeds: &[&[u8]] = &[
-            b"collateral_vault",
-            loan_request_key.as_ref(),
-            &[self.collateral_vault.bump],
-        ];
-        let signer_seeds = &[collateral_seeds];
-
-        // Transfer 90% to the lender.
-        {
-            let cpi_accounts = Transfer {
-                from: self.collateral_vault.to_account_info(),
-                to: self.lender.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                self.system_program.to_account_info(),
-                cpi_accounts,
-                signer_seeds,
-            );
-            transfer(cpi_ctx, lender_amount)?;
+    /// Retrieves the collateral recorded on the loan, excluding the vault's rent deposit.
+    pub fn get_collateral(&self) -> Result<u64> {
+        if self.loan_request.collateral_mint.is_some() {
+            let token_vault = self.collateral_token_vault.as_ref().ok_or(ErrorCode::MissingCollateralAccount)?;
+            return get_token_vault_collateral(token_vault.amount, self.loan_request.collateral);
         }
-        
-        // Transfer 10% fee to the protocol vault.
-        {
-            let cpi_accounts = Transfer {
-                from: self.collateral_vault.to_account_info(),
-                to: self.protocol_vault.to_account_info(),
-            };
-            let cpi_ctx = CpiContext::new_with_signer(
-                self.system_program.to_account_info(),
-                cpi_accounts,
-                signer_seeds,
-            );
-            transfer(cpi_ctx, fee)?;
+        get_vault_collateral(&self.collateral_vault.to_account_info(), self.loan_request.collateral)
+    }
+
+    /// Accounts paying out the seized collateral.
+    fn seized_collateral(&self) -> SeizedCollateral<'_, 'info> {
+        SeizedCollateral {
+            loan_request: &self.loan_request,
+            collateral_vault: &self.collateral_vault,
+            lender: self.lender.as_ref(),
+            borrower: self.borrower.as_ref(),
+            protocol_vault: self.protocol_vault.as_ref().as_ref(),
+            collateral_token_vault: self.collateral_token_vault.as_deref(),
+            lender_collateral_account: self.lender_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+            borrower_collateral_account: self.borrower_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+            protocol_collateral_vault: self.protocol_collateral_vault.as_ref().map(|account| account.as_ref().as_ref()),
+            token_program: self.token_program.as_ref().map(|program| program.as_ref()),
         }
-        Ok(())
     }
 
 
 
-    // remove the loan request from the loan registry
+    // remove the loan request from its loan registry page
     fn remove_from_loan_registry(&mut self) -> Result<()> {
         let loan_request_key = self.loan_request.key();
-        
-        // Find the index of the loan request in the registry
-        let position = self.loan_registry.loan_requests.iter()
-            .position(|&pubkey| pubkey == loan_request_key)
-            .ok_or(ErrorCode::NotFoundInRegistry)?;
-        
-        // Remove the loan request from the registry
-        self.loan_registry.loan_requests.remove(position);
-        
-        // Decrement the total loans counter
-        self.loan_registry.total_loans = self.loan_registry.total_loans
-            .checked_sub(1)
-            .ok_or(ErrorCode::CalculationError)?;
-            
-        Ok(())
+        remove_from_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
     }
 }
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::state::LenderPositionState;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct CloseLenderPosition<'info> {
+    /// Lender owning the position, receives its rent back.
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// Position of a syndicated loan that has been paid out or refunded.
+    #[account(
+        mut,
+        close = lender,
+        has_one = lender,
+        seeds = [b"lender_position", lender_position.loan_request.as_ref(), lender.key().as_ref()],
+        bump = lender_position.bump,
+        constraint = lender_position.settled @ ErrorCode::PositionNotSettled
+    )]
+    pub lender_position: Account<'info, LenderPositionState>,
+}
+
+impl<'info> CloseLenderPosition<'info> {
+    /// Nothing is left to pay on a settled position, closing it only returns the rent.
+    pub fn close_lender_position(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
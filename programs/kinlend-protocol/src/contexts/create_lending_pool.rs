@@ -0,0 +1,77 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::constants::USDC_DECIMALS;
+use crate::helpers::{check_pool_policy, check_usdc_mint_address};
+use crate::state::{ConfigState, LendingPoolState, PoolPolicy};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct CreateLendingPool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    //lending pool account, also the authority of the share mint and the pool vault
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + LendingPoolState::INIT_SPACE,
+        seeds = [b"lending_pool"],
+        bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    //mint of the pool shares handed to depositors, same decimals as USDC
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = USDC_DECIMALS as u8,
+        mint::authority = lending_pool,
+        seeds = [b"pool_share_mint"],
+        bump
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    //pool vault holding the USDC not lent out
+    #[account(
+        init,
+        payer = admin,
+        token::mint = usdc_mint,
+        token::authority = lending_pool,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateLendingPool<'info> {
+    pub fn create_lending_pool(&mut self, policy: PoolPolicy, bumps: CreateLendingPoolBumps) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+        check_pool_policy(&policy, self.config.terms.collateral_ratio_bps)?;
+
+        self.lending_pool.set_inner(LendingPoolState {
+            lent_principal: 0,
+            unsettled_earnings: 0,
+            seized_collateral: 0,
+            policy,
+            bump: bumps.lending_pool,
+        });
+
+        Ok(())
+    }
+}
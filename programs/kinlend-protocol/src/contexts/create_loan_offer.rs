@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::constants::MAX_COLLATERAL_RATIO_BPS;
+use crate::helpers::{add_to_offer_registry, check_usdc_mint_address};
+use crate::state::{ConfigState, LoanOfferState, OfferRegistryPageState, OfferRegistryState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(offer_id: u64, amount: u64, max_amount: u64, max_duration_days: u64, min_collateral_ratio_bps: u16, page_index: u64)]
+pub struct CreateLoanOffer<'info> {
+    // Lender posting the offer, pays for the offer accounts and escrows the USDC
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    // Creating LoanOfferState Account
+    #[account(
+        init,
+        space = 8 + LoanOfferState::INIT_SPACE,
+        payer = lender,
+        seeds = [b"loan_offer", lender.key().as_ref(), &offer_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_offer: Box<Account<'info, LoanOfferState>>,
+
+    // Escrow holding the USDC lent out by the offer, owned by the offer PDA
+    #[account(
+        init,
+        payer = lender,
+        token::mint = usdc_mint,
+        token::authority = loan_offer,
+        seeds = [b"offer_escrow", loan_offer.key().as_ref()],
+        bump
+    )]
+    pub offer_escrow: Box<Account<'info, TokenAccount>>,
+
+    // Lender's USDC token account funding the escrow
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = lender_usdc_account.owner == lender.key()
+    )]
+    pub lender_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    // Root offer registry Account
+    #[account(
+        mut,
+        seeds = [b"offer_registry"],
+        bump
+    )]
+    pub offer_registry: Box<Account<'info, OfferRegistryState>>,
+
+    // Offer registry page chosen by the lender, must have a free slot
+    #[account(
+        mut,
+        seeds = [b"offer_registry_page", &page_index.to_le_bytes()],
+        bump = offer_registry_page.bump
+    )]
+    pub offer_registry_page: Box<Account<'info, OfferRegistryPageState>>,
+
+    // Config Account, the offer can't ask for less collateral than the protocol ratio
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CreateLoanOffer<'info> {
+    /// Posts a standing offer backed by `amount` of escrowed USDC.
+    /// Borrowers can take loans of up to `max_amount` each until the escrow runs out.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_loan_offer(
+        &mut self,
+        offer_id: u64,
+        amount: u64,
+        max_amount: u64,
+        max_duration_days: u64,
+        min_collateral_ratio_bps: u16,
+        page_index: u64,
+        bumps: CreateLoanOfferBumps,
+    ) -> Result<()> {
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        require!(
+            max_amount > 0
+                && max_duration_days > 0
+                && min_collateral_ratio_bps >= self.config.terms.collateral_ratio_bps
+                && min_collateral_ratio_bps <= MAX_COLLATERAL_RATIO_BPS,
+            ErrorCode::InvalidLoanOffer
+        );
+
+        self.loan_offer.set_inner(LoanOfferState {
+            offer_id,
+            lender: self.lender.key(),
+            max_amount,
+            available_amount: amount,
+            max_duration_days,
+            min_collateral_ratio_bps,
+            registry_page: page_index,
+            bump: bumps.loan_offer,
+        });
+
+        // Escrow the USDC lent out by the offer.
+        let cpi_accounts = Transfer {
+            from: self.lender_usdc_account.to_account_info(),
+            to: self.offer_escrow.to_account_info(),
+            authority: self.lender.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, amount)?;
+
+        let loan_offer_key = self.loan_offer.key();
+        add_to_offer_registry(&mut self.offer_registry, &mut self.offer_registry_page, loan_offer_key)
+    }
+}
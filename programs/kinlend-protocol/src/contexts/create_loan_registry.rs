@@ -28,8 +28,9 @@ impl<'info> CreateLoanRegistry<'info> {
 
         //creating loan registry
         self.loan_registry.set_inner(LoanRegistryState{
+            page_count: 0,
             total_loans: 0,
-            loan_requests: Vec::new()
+            free_slots: 0
         });
 
         Ok(())
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::LOAN_REGISTRY_PAGE_SIZE;
+use crate::state::{LoanRegistryPageState, LoanRegistryState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct CreateLoanRegistryPage<'info> {
+    //anyone can pay for a new page when the existing ones are full
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    //pages are created in order, the next page index is the current page count
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + LoanRegistryPageState::INIT_SPACE,
+        seeds = [b"loan_registry_page".as_ref(), &loan_registry.page_count.to_le_bytes()],
+        bump
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    //program
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CreateLoanRegistryPage<'info> {
+
+    pub fn create_loan_registry_page(&mut self, bumps: CreateLoanRegistryPageBumps) -> Result<()> {
+
+        //creating loan registry page
+        self.loan_registry_page.set_inner(LoanRegistryPageState {
+            page_index: self.loan_registry.page_count,
+            loan_requests: Vec::new(),
+            bump: bumps.loan_registry_page,
+        });
+
+        //tracking the new page and its empty slots in the root
+        self.loan_registry.page_count = self.loan_registry.page_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        self.loan_registry.free_slots = self.loan_registry.free_slots
+            .checked_add(LOAN_REGISTRY_PAGE_SIZE as u64)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
-use anchor_lang::{prelude::*, solana_program::native_token::LAMPORTS_PER_SOL, system_program::{transfer, Transfer}};
+use anchor_lang::{prelude::*, system_program::{transfer, Transfer}};
 
-use crate::{errors::ErrorCode, state::{CollateralVaultState, LoanRegistryState, LoanRequestState}};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{constants::SOL_DECIMALS, errors::ErrorCode, helpers::{add_to_loan_registry, check_allowed_lenders, check_apr_bps, get_sol_price}, math::{required_collateral, OraclePrice}, state::{CollateralVaultState, ConfigState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus}};
 
 #[derive(Accounts)]
-#[instruction(loan_id:u64)]
+#[instruction(loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64, page_index: u64)]
 pub struct CreateLoanRequest<'info> {
     // Borrower is the one who creates the loan request account so, he/she will be paying for account creation.
     #[account(mut)]
@@ -37,28 +39,57 @@ pub struct CreateLoanRequest<'info> {
     )]
     pub loan_registry: Box<Account<'info, LoanRegistryState>>,
 
+    // Registry page chosen by the borrower, must have a free slot
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &page_index.to_le_bytes()],
+        bump = loan_registry_page.bump
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    // Config Account, its current terms are snapshotted into the loan request
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    // Pyth pull-oracle price update for SOL/USD, used to price the collateral on-chain
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
     pub system_program: Program<'info, System>,
 }
 
 // Implementing CreateLoanRequest
 impl<'info> CreateLoanRequest<'info> {
+        #[allow(clippy::too_many_arguments)]
         pub fn create_loan_request(
         &mut self, 
         loan_id: u64, 
         loan_amount: u64, 
         collateral: u64,       // collateral provided in lamports
         duration_days: u64,
-        sol_price: u64,        // SOL price in USDC smallest unit (passed directly)
+        page_index: u64,
+        apr_bps: Option<u16>,  // optional yearly interest replacing the flat lender fee
+        allowed_lenders: Option<Vec<Pubkey>>, // optional lenders allowed to fund a private request
         bumps: CreateLoanRequestBumps,
     ) -> Result<()> {
-        // 1. Calculate the required collateral (in lamports) based on the loan amount.
-        let required_collateral = self.calculate_required_collateral(loan_amount, sol_price)?;
+        // 0. The loan needs an amount and a duration, an APR and an allowlist, when chosen, must be within bounds.
+        require!(loan_amount > 0, ErrorCode::InvalidAmount);
+        require!(duration_days > 0, ErrorCode::InvalidDuration);
+        check_apr_bps(apr_bps)?;
+        let allowed_lenders = allowed_lenders.unwrap_or_default();
+        check_allowed_lenders(&allowed_lenders)?;
+
+        // 1. Read the SOL price from the oracle and calculate the required collateral (in lamports).
+        let sol_price = get_sol_price(&self.price_update)?;
+        let required_collateral = self.calculate_required_collateral(loan_amount, &sol_price)?;
         
         // 2. Verify that the provided collateral is sufficient.
         self.verify_collateral(collateral, required_collateral)?;
         
         // 3. Initialize the LoanRequest state.
-        self.initialize_loan_request(loan_id, loan_amount, collateral, duration_days)?;
+        self.initialize_loan_request(loan_id, loan_amount, collateral, duration_days, page_index, apr_bps, allowed_lenders)?;
         
         // 4. Initialize the CollateralVault state.
         self.initialize_collateral_vault(bumps.collateral_vault)?;
@@ -66,28 +97,20 @@ impl<'info> CreateLoanRequest<'info> {
         // 5. Transfer the provided collateral from the borrower's wallet into the collateral vault.
         self.transfer_collateral_to_vault(collateral)?;
         
-        // 6. Update the loan registry by adding the new loan request's key.
+        // 6. Update the loan registry page by adding the new loan request's key,
+        //    this also increments the total number of loans in the registry.
         self.update_loan_registry()?;
         
-        // 7. Increment the total number of loans in the registry.
-        self.loan_registry.total_loans = self.loan_registry.total_loans
-            .checked_add(1)
-            .ok_or(ErrorCode::CalculationError)?;
-        
         Ok(())
     }
     
-    /// Calculates the required collateral (in lamports) using the formula:
-    /// required_collateral = (loan_amount * 150 * LAMPORTS_PER_SOL) / (100 * sol_price)
+    /// Calculates the required collateral (in lamports) so that its USD value meets the configured ratio:
+    /// required_collateral = ceil(loan_amount * collateral_ratio / sol_price)
     /// - loan_amount: in USDC smallest unit (e.g., 1 USDC = 1_000_000)
-    /// - sol_price: in USDC smallest unit per SOL (e.g., 20 USDC per SOL = 20_000_000)
-    fn calculate_required_collateral(&self, loan_amount: u64, sol_price: u64) -> Result<u64> {
-        let numerator = loan_amount
-            .checked_mul(150).ok_or(ErrorCode::CalculationError)?
-            .checked_mul(LAMPORTS_PER_SOL).ok_or(ErrorCode::CalculationError)?;
-        let denominator = 100_u64
-            .checked_mul(sol_price).ok_or(ErrorCode::CalculationError)?;
-        numerator.checked_div(denominator).ok_or(ErrorCode::CalculationError.into())
+    /// - sol_price: oracle price of one SOL, exponent is handled by the math module
+    fn calculate_required_collateral(&self, loan_amount: u64, sol_price: &OraclePrice) -> Result<u64> {
+        let collateral_ratio_bps = self.config.terms.collateral_ratio_bps as u64;
+        required_collateral(loan_amount, collateral_ratio_bps, SOL_DECIMALS, sol_price)
     }
     
     /// Verifies that the provided collateral (in lamports) is at least the required amount.
@@ -97,15 +120,31 @@ impl<'info> CreateLoanRequest<'info> {
     }
     
     /// Initializes the LoanRequest state.
-    fn initialize_loan_request(&mut self, loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    fn initialize_loan_request(&mut self, loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64, registry_page: u64, apr_bps: Option<u16>, allowed_lenders: Vec<Pubkey>) -> Result<()> {
         self.loan_request.set_inner(LoanRequestState {
             loan_id,
             loan_amount,
+            outstanding_principal: loan_amount,
+            apr_bps,
             collateral, // stored in lamports
+            collateral_mint: None,
+            collateral_haircut_bps: 0,
             duration_days,
             borrower: self.borrower.key(),
             lender: None,
+            allowed_lenders,
             repayment_time: None,
+            terms: self.config.terms,
+            status: LoanStatus::Requested,
+            registry_page,
+            pending_extension: None,
+            extension_count: 0,
+            extension_fees: 0,
+            subscribed_amount: 0,
+            tranche_count: 0,
+            position_mint: None,
+            position_count: 0,
         });
         Ok(())
     }
@@ -128,9 +167,9 @@ impl<'info> CreateLoanRequest<'info> {
         transfer(cpi_ctx, collateral)
     }
     
-    /// Updates the loan registry by adding the new loan request's key.
+    /// Updates the loan registry page by adding the new loan request's key.
     fn update_loan_registry(&mut self) -> Result<()> {
-        self.loan_registry.loan_requests.push(self.loan_request.key());
-        Ok(())
+        let loan_request_key = self.loan_request.key();
+        add_to_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
     }
 }
@@ -0,0 +1,185 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::{errors::ErrorCode, helpers::{add_to_loan_registry, check_allowed_lenders, check_apr_bps, get_oracle_price}, math::{apply_haircut, required_collateral, OraclePrice}, state::{CollateralMintState, CollateralVaultState, ConfigState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus}};
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64, page_index: u64)]
+pub struct CreateLoanRequestToken<'info> {
+    // Borrower creates the loan request and pays for account creation
+    #[account(mut)]
+    borrower: Signer<'info>,
+
+    // Creating LoanRequestState Account
+    #[account(
+        init, 
+        space = 8 + LoanRequestState::INIT_SPACE,
+        payer = borrower, 
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    // Collateral vault PDA, it owns the token vault below
+    #[account(
+        init,
+        payer = borrower,
+        space = 8 + CollateralVaultState::INIT_SPACE,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    // Token account holding the SPL collateral, owned by the collateral vault PDA
+    #[account(
+        init,
+        payer = borrower,
+        token::mint = collateral_mint,
+        token::authority = collateral_vault,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Box<Account<'info, TokenAccount>>,
+
+    // SPL mint used as collateral
+    pub collateral_mint: Box<Account<'info, Mint>>,
+
+    // Whitelisted mint config, stores the oracle feed and haircut of the collateral
+    #[account(
+        seeds = [b"collateral_mint", collateral_mint.key().as_ref()],
+        bump = collateral_mint_config.bump,
+        constraint = collateral_mint_config.enabled @ ErrorCode::InvalidCollateralMint
+    )]
+    pub collateral_mint_config: Box<Account<'info, CollateralMintState>>,
+
+    // Borrower's token account the collateral is taken from
+    #[account(
+        mut,
+        constraint = borrower_collateral_account.mint == collateral_mint.key() @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    // Root registry Account
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump 
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    // Registry page chosen by the borrower, must have a free slot
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &page_index.to_le_bytes()],
+        bump = loan_registry_page.bump
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    // Config Account, its current terms are snapshotted into the loan request
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    // Pyth pull-oracle price update for the collateral mint's feed
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Implementing CreateLoanRequestToken
+impl<'info> CreateLoanRequestToken<'info> {
+        #[allow(clippy::too_many_arguments)]
+        pub fn create_loan_request_token(
+        &mut self, 
+        loan_id: u64, 
+        loan_amount: u64, 
+        collateral: u64,       // collateral provided in base units of the mint
+        duration_days: u64,
+        page_index: u64,
+        apr_bps: Option<u16>,  // optional yearly interest replacing the flat lender fee
+        allowed_lenders: Option<Vec<Pubkey>>, // optional lenders allowed to fund a private request
+        bumps: CreateLoanRequestTokenBumps,
+    ) -> Result<()> {
+        // 0. The loan needs an amount and a duration, an APR and an allowlist, when chosen, must be within bounds.
+        require!(loan_amount > 0, ErrorCode::InvalidAmount);
+        require!(duration_days > 0, ErrorCode::InvalidDuration);
+        check_apr_bps(apr_bps)?;
+        let allowed_lenders = allowed_lenders.unwrap_or_default();
+        check_allowed_lenders(&allowed_lenders)?;
+
+        // 1. Read the collateral price from the mint's feed and discount it by the mint's haircut.
+        let market_price = get_oracle_price(&self.price_update, &self.collateral_mint_config.feed_id)?;
+        let collateral_price = apply_haircut(&market_price, self.collateral_mint_config.haircut_bps)?;
+        let required_collateral = self.calculate_required_collateral(loan_amount, &collateral_price)?;
+
+        // 2. Verify that the provided collateral is sufficient.
+        require!(collateral >= required_collateral, ErrorCode::InsuffientCollateral);
+
+        // 3. Initialize the LoanRequest and CollateralVault states.
+        self.initialize_loan_request(loan_id, loan_amount, collateral, duration_days, page_index, apr_bps, allowed_lenders)?;
+        self.collateral_vault.set_inner(CollateralVaultState {
+            bump: bumps.collateral_vault
+        });
+
+        // 4. Transfer the collateral tokens from the borrower into the token vault.
+        self.transfer_collateral_to_vault(collateral)?;
+
+        // 5. List the new loan request in the chosen registry page.
+        let loan_request_key = self.loan_request.key();
+        add_to_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
+    }
+
+    /// Calculates the required collateral (in base units of the mint) at the haircut price.
+    fn calculate_required_collateral(&self, loan_amount: u64, collateral_price: &OraclePrice) -> Result<u64> {
+        let collateral_ratio_bps = self.config.terms.collateral_ratio_bps as u64;
+        required_collateral(loan_amount, collateral_ratio_bps, self.collateral_mint_config.decimals as u32, collateral_price)
+    }
+
+    /// Initializes the LoanRequest state, recording the collateral mint and its haircut.
+    #[allow(clippy::too_many_arguments)]
+    fn initialize_loan_request(&mut self, loan_id: u64, loan_amount: u64, collateral: u64, duration_days: u64, registry_page: u64, apr_bps: Option<u16>, allowed_lenders: Vec<Pubkey>) -> Result<()> {
+        self.loan_request.set_inner(LoanRequestState {
+            loan_id,
+            loan_amount,
+            outstanding_principal: loan_amount,
+            apr_bps,
+            collateral, // stored in base units of the mint
+            collateral_mint: Some(self.collateral_mint.key()),
+            collateral_haircut_bps: self.collateral_mint_config.haircut_bps,
+            duration_days,
+            borrower: self.borrower.key(),
+            lender: None,
+            allowed_lenders,
+            repayment_time: None,
+            terms: self.config.terms,
+            status: LoanStatus::Requested,
+            registry_page,
+            pending_extension: None,
+            extension_count: 0,
+            extension_fees: 0,
+            subscribed_amount: 0,
+            tranche_count: 0,
+            position_mint: None,
+            position_count: 0,
+        });
+        Ok(())
+    }
+
+    /// Transfers the collateral tokens from the borrower's token account to the token vault.
+    fn transfer_collateral_to_vault(&self, collateral: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.borrower_collateral_account.to_account_info(),
+            to: self.collateral_token_vault.to_account_info(),
+            authority: self.borrower.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, collateral)
+    }
+}
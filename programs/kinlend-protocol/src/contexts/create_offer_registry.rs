@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::state::OfferRegistryState;
+
+
+#[derive(Accounts)]
+pub struct CreateOfferRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + OfferRegistryState::INIT_SPACE,
+        seeds = [b"offer_registry"],
+        bump
+    )]
+    pub offer_registry: Box<Account<'info, OfferRegistryState>>,
+
+    //program
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CreateOfferRegistry<'info> {
+
+    pub fn create_offer_registry(&mut self) -> Result<()> {
+
+        //creating offer registry
+        self.offer_registry.set_inner(OfferRegistryState{
+            page_count: 0,
+            total_offers: 0,
+            free_slots: 0
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::OFFER_REGISTRY_PAGE_SIZE;
+use crate::state::{OfferRegistryPageState, OfferRegistryState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct CreateOfferRegistryPage<'info> {
+    //anyone can pay for a new page when the existing ones are full
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"offer_registry"],
+        bump
+    )]
+    pub offer_registry: Box<Account<'info, OfferRegistryState>>,
+
+    //pages are created in order, the next page index is the current page count
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OfferRegistryPageState::INIT_SPACE,
+        seeds = [b"offer_registry_page".as_ref(), &offer_registry.page_count.to_le_bytes()],
+        bump
+    )]
+    pub offer_registry_page: Box<Account<'info, OfferRegistryPageState>>,
+
+    //program
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> CreateOfferRegistryPage<'info> {
+
+    pub fn create_offer_registry_page(&mut self, bumps: CreateOfferRegistryPageBumps) -> Result<()> {
+
+        //creating offer registry page
+        self.offer_registry_page.set_inner(OfferRegistryPageState {
+            page_index: self.offer_registry.page_count,
+            loan_offers: Vec::new(),
+            bump: bumps.offer_registry_page,
+        });
+
+        //tracking the new page and its empty slots in the root
+        self.offer_registry.page_count = self.offer_registry.page_count
+            .checked_add(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        self.offer_registry.free_slots = self.offer_registry.free_slots
+            .checked_add(OFFER_REGISTRY_PAGE_SIZE as u64)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+}
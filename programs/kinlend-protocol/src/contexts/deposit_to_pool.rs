@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer};
+
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::helpers::{calculate_pool_value, get_sol_price};
+use crate::math::shares_for_deposit;
+use crate::state::LendingPoolState;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct DepositToPool<'info> {
+    // Depositor putting USDC in the pool
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    // Pool vault receiving the deposit
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_share_mint"],
+        bump
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    // Depositor's USDC token account
+    #[account(
+        mut,
+        constraint = depositor_usdc_account.mint == pool_usdc_vault.mint,
+        constraint = depositor_usdc_account.owner == depositor.key()
+    )]
+    pub depositor_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    // Depositor's associated token account receiving the pool shares
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        associated_token::mint = share_mint,
+        associated_token::authority = depositor
+    )]
+    pub depositor_share_account: Box<Account<'info, TokenAccount>>,
+
+    // Pyth pull-oracle price update for SOL/USD, values the SOL seized by the pool
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> DepositToPool<'info> {
+    /// Deposits `amount` of USDC and mints pool shares at the current pool value.
+    pub fn deposit_to_pool(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let sol_price = get_sol_price(&self.price_update)?;
+        let pool_value = calculate_pool_value(&self.lending_pool, self.pool_usdc_vault.amount, &sol_price)?;
+        let shares = shares_for_deposit(amount, self.share_mint.supply, pool_value)?;
+        require!(shares > 0, ErrorCode::PoolAmountTooSmall);
+
+        let cpi_accounts = Transfer {
+            from: self.depositor_usdc_account.to_account_info(),
+            to: self.pool_usdc_vault.to_account_info(),
+            authority: self.depositor.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, amount)?;
+
+        let pool_seeds: &[&[u8]] = &[b"lending_pool", &[self.lending_pool.bump]];
+        let signer_seeds = &[pool_seeds];
+        let cpi_accounts = MintTo {
+            mint: self.share_mint.to_account_info(),
+            to: self.depositor_share_account.to_account_info(),
+            authority: self.lending_pool.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(self.token_program.to_account_info(), cpi_accounts, signer_seeds);
+        mint_to(cpi_ctx, shares)
+    }
+}
@@ -0,0 +1,179 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
+
+use crate::helpers::{get_loan_lender, release_token_collateral, remove_from_loan_registry, transition_loan_status};
+use crate::state::{CollateralVaultState, ConfigState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct ForgiveLoan<'info> {
+    /// The lender forgiving the debt. Must be the one recorded as the lender in the loan request,
+    /// or the holder of its position NFT.
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// Borrower of the loan, receives all of the collateral.
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    /// Loan Request account, kept as a record and marked as forgiven.
+    /// A syndicated loan can't be forgiven, no single lender can write off the other tranches.
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump,
+        constraint = loan_request.tranche_count == 0 @ ErrorCode::SyndicatedLoanNotSupported,
+        constraint = get_loan_lender(&loan_request, position_token_account.as_deref().map(|account| &**account)) == Some(lender.key()) @ ErrorCode::UnauthorizedLender
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    /// Collateral Vault, closed to the borrower with its SOL collateral and rent deposit.
+    #[account(
+        mut,
+        close = borrower,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    /// Loan Registry account (tracking all loans).
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump,
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    /// Config account storing the forgiveness fee and the usdc mint.
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    /// Lender's USDC account paying the forgiveness fee. Only needed when the fee is set.
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == config.usdc_mint @ ErrorCode::IncorrectUsdcMintAddress,
+        constraint = lender_usdc_account.owner == lender.key()
+    )]
+    pub lender_usdc_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Token account holding the position NFT of the loan, only for loans funded with `fund_loan`.
+    /// Its owner is the lender owed the loan.
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Protocol USDC vault receiving the forgiveness fee.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault_usdc"],
+        bump
+    )]
+    pub protocol_vault_usdc: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Token vault holding SPL collateral, only for loans backed by a whitelisted mint.
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Borrower's token account receiving the SPL collateral.
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ForgiveLoan<'info> {
+    /// Writes the loan off:
+    /// - the loan is marked as forgiven and removed from the registry,
+    /// - all collateral goes back to the borrower,
+    /// - no repayment fee is charged, only the configured flat forgiveness fee paid by the lender.
+    ///
+    /// Syndicated loans are rejected.
+    pub fn forgive_loan(&mut self) -> Result<()> {
+        // Only a funded loan can be forgiven.
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Forgiven)?;
+
+        self.charge_forgiveness_fee()?;
+        self.return_token_collateral()?;
+
+        // Nothing is owed anymore.
+        self.loan_request.outstanding_principal = 0;
+
+        // SOL collateral and the vault's rent deposit go back to the borrower when the vault is closed.
+        let loan_request_key = self.loan_request.key();
+        remove_from_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
+    }
+
+    /// Transfers the flat forgiveness fee from the lender to the protocol, when configured.
+    fn charge_forgiveness_fee(&self) -> Result<()> {
+        let fee = self.config.forgiveness_fee;
+        if fee == 0 {
+            return Ok(());
+        }
+
+        let (Some(lender_usdc_account), Some(protocol_vault_usdc), Some(token_program)) = (
+            &self.lender_usdc_account,
+            &self.protocol_vault_usdc,
+            &self.token_program,
+        ) else {
+            return Err(ErrorCode::MissingFeeAccount.into());
+        };
+
+        let cpi_accounts = Transfer {
+            from: lender_usdc_account.to_account_info(),
+            to: protocol_vault_usdc.to_account_info(),
+            authority: self.lender.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, fee)
+    }
+
+    /// Returns SPL collateral to the borrower and closes the token vault.
+    fn return_token_collateral(&self) -> Result<()> {
+        if self.loan_request.collateral_mint.is_none() {
+            return Ok(());
+        }
+
+        let (Some(token_vault), Some(borrower_account), Some(token_program)) = (
+            &self.collateral_token_vault,
+            &self.borrower_collateral_account,
+            &self.token_program,
+        ) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+
+        release_token_collateral(
+            &token_program.to_account_info(),
+            &token_vault.to_account_info(),
+            &borrower_account.to_account_info(),
+            &self.borrower.to_account_info(),
+            &self.collateral_vault.to_account_info(),
+            self.loan_request.key(),
+            self.collateral_vault.bump,
+            token_vault.amount,
+        )
+    }
+}
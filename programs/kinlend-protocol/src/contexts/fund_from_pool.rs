@@ -0,0 +1,182 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::constants::SOL_DECIMALS;
+use crate::helpers::{calculate_pool_value, check_allowed_lender, check_usdc_mint_address, get_sol_price, start_loan, transfer_loan_funds};
+use crate::math::{apply_bps, required_collateral, OraclePrice};
+use crate::state::{ConfigState, LendingPoolState, LoanRequestState, PoolLoanState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct FundFromPool<'info> {
+    /// Anyone can ask the pool to fund an eligible request, the keeper pays the rent of the pool loan accounts.
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    /// Pool vault funding the loan.
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump,
+        constraint = pool_usdc_vault.mint == usdc_mint.key() @ ErrorCode::IncorrectUsdcMintAddress
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Loan Request account funded by the pool.
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    /// Pool loan tracking the principal, its PDA is recorded as the lender of the loan.
+    #[account(
+        init,
+        payer = keeper,
+        space = 8 + PoolLoanState::INIT_SPACE,
+        seeds = [b"pool_loan", loan_request.key().as_ref()],
+        bump
+    )]
+    pub pool_loan: Box<Account<'info, PoolLoanState>>,
+
+    /// USDC account of the pool loan, receiving the repayments until they are settled into the pool vault.
+    #[account(
+        init,
+        payer = keeper,
+        token::mint = usdc_mint,
+        token::authority = pool_loan,
+        seeds = [b"pool_loan_usdc", loan_request.key().as_ref()],
+        bump
+    )]
+    pub pool_loan_usdc: Box<Account<'info, TokenAccount>>,
+
+    /// Borrower's main account, receiving USDC through its associated token account.
+    #[account(
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    /// Borrower's USDC associated token account.
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = borrower
+    )]
+    pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Pyth pull-oracle price update for SOL/USD, prices the collateral and the SOL seized by the pool.
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FundFromPool<'info> {
+    /// Funds the loan request from the pool vault if it meets the pool policy, at no less than the pool collateral ratio.
+    /// The pool loan PDA becomes the lender, so repayments are settled back into the pool with `settle_pool_loan`.
+    pub fn fund_from_pool(&mut self, bumps: FundFromPoolBumps) -> Result<()> {
+        // Ensure the loan hasn't already been funded and start it in the pool loan's name.
+        start_loan(&mut self.loan_request, self.pool_loan.key())?;
+
+        // A loan partly funded by tranches can only be completed with more tranches.
+        require!(self.loan_request.subscribed_amount == 0, ErrorCode::LoanPartiallySubscribed);
+
+        // A private request must name the pool among its allowed lenders.
+        check_allowed_lender(&self.loan_request.allowed_lenders, self.lending_pool.key())?;
+
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+
+        let sol_price = get_sol_price(&self.price_update)?;
+        self.verify_pool_policy(&sol_price)?;
+        self.verify_pool_exposure(&sol_price)?;
+
+        // The pool ratio replaces a lower protocol ratio, so the collateral can't be withdrawn below it.
+        let terms = &mut self.loan_request.terms;
+        terms.collateral_ratio_bps = terms.collateral_ratio_bps.max(self.lending_pool.policy.min_collateral_ratio_bps);
+
+        let loan_amount = self.loan_request.loan_amount;
+        self.pool_loan.set_inner(PoolLoanState {
+            loan_request: self.loan_request.key(),
+            principal: loan_amount,
+            keeper: self.keeper.key(),
+            bump: bumps.pool_loan,
+        });
+        self.lending_pool.lent_principal = self.lending_pool.lent_principal
+            .checked_add(loan_amount)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        let pool_seeds: &[&[u8]] = &[b"lending_pool", &[self.lending_pool.bump]];
+        transfer_loan_funds(
+            &self.token_program.to_account_info(),
+            &self.pool_usdc_vault.to_account_info(),
+            &self.borrower_usdc_account.to_account_info(),
+            &self.lending_pool.to_account_info(),
+            &[pool_seeds],
+            loan_amount,
+        )
+    }
+
+    /// Verifies the request against the pool policy: SOL collateral worth at least the pool ratio,
+    /// no smaller, no larger and no longer than the pool allows.
+    fn verify_pool_policy(&self, sol_price: &OraclePrice) -> Result<()> {
+        let policy = &self.lending_pool.policy;
+        let loan_request = &self.loan_request;
+        require!(
+            loan_request.collateral_mint.is_none()
+                && loan_request.loan_amount >= policy.min_loan_amount
+                && loan_request.loan_amount <= policy.max_loan_amount
+                && loan_request.duration_days <= policy.max_duration_days,
+            ErrorCode::LoanOutsidePoolPolicy
+        );
+
+        let required = required_collateral(
+            loan_request.loan_amount,
+            policy.min_collateral_ratio_bps as u64,
+            SOL_DECIMALS,
+            sol_price,
+        )?;
+        require!(loan_request.collateral >= required, ErrorCode::LoanOutsidePoolPolicy);
+
+        Ok(())
+    }
+
+    /// Verifies the pool has the USDC and that its lent principal stays under the utilization cap.
+    fn verify_pool_exposure(&self, sol_price: &OraclePrice) -> Result<()> {
+        let loan_amount = self.loan_request.loan_amount;
+        let liquidity = self.pool_usdc_vault.amount;
+        require!(loan_amount <= liquidity, ErrorCode::InsufficientPoolLiquidity);
+
+        let pool_value = calculate_pool_value(&self.lending_pool, liquidity, sol_price)?;
+        let max_lent = apply_bps(pool_value, self.lending_pool.policy.max_utilization_bps as u64)?;
+        let lent = self.lending_pool.lent_principal
+            .checked_add(loan_amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(lent <= max_lent, ErrorCode::PoolExposureExceeded);
+
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
-use crate::helpers::calculate_repayment_time;
+use crate::helpers::{check_allowed_lender, mint_position_nft, start_loan, transfer_loan_funds};
 use crate::state::{ConfigState, LoanRequestState};
 use crate::errors::ErrorCode;
 
@@ -16,7 +16,6 @@ pub struct FundLoan<'info> {
     /// Configuration account storing protocol settings.
     /// It is a PDA seeded with "config".
     #[account(
-        mut,
         seeds = [b"config"],
         bump
     )]
@@ -31,9 +30,12 @@ pub struct FundLoan<'info> {
     )]
     pub loan_request: Box<Account<'info, LoanRequestState>>,
 
-    /// Borrower’s main account (system account). 
+    /// Borrower’s main account (system account), must be the borrower recorded in the loan request.
     /// This account will receive USDC tokens through its associated token account.
-    #[account(mut)]
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
     pub borrower: SystemAccount<'info>,
 
     /// Lender's USDC associated token account.
@@ -54,6 +56,27 @@ pub struct FundLoan<'info> {
     )]
     pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
 
+    /// Position NFT of the loan, minted once to the lender. Its holder is owed the repayment or the collateral.
+    /// Seeded with the loan's position count, so every position of the loan gets its own mint.
+    #[account(
+        init,
+        payer = lender,
+        mint::decimals = 0,
+        mint::authority = loan_request,
+        seeds = [b"position_mint", loan_request.key().as_ref(), &loan_request.position_count.to_le_bytes()],
+        bump
+    )]
+    pub position_mint: Box<Account<'info, Mint>>,
+
+    /// Lender's associated token account receiving the position NFT.
+    #[account(
+        init,
+        payer = lender,
+        associated_token::mint = position_mint,
+        associated_token::authority = lender
+    )]
+    pub lender_position_token_account: Box<Account<'info, TokenAccount>>,
+
     /// The USDC Mint account.
     pub usdc_mint: Account<'info, Mint>,
 
@@ -66,30 +89,32 @@ pub struct FundLoan<'info> {
 }
 
 impl<'info> FundLoan<'info> {
-    pub fn fund_loan(&mut self) -> Result<()> {
-        // Ensure the loan hasn't already been funded.
-        self.verify_not_funded()?;
+    pub fn fund_loan(&mut self, loan_request_bump: u8) -> Result<()> {
+        // Ensure the loan hasn't already been funded, then mark it as funded,
+        // record the lender's key and set the repayment deadline.
+        start_loan(&mut self.loan_request, self.lender.key())?;
 
-        //Verify the provided USDC mint matches the one in the configuration.
-        self.verify_usdc_mint()?;
+        // A loan partly funded by tranches can only be completed with more tranches.
+        require!(self.loan_request.subscribed_amount == 0, ErrorCode::LoanPartiallySubscribed);
 
-        //Record the lender's key in the loan request.
-        self.update_loan_request_with_lender()?;
+        // A private request can only be funded by one of its allowed lenders.
+        self.verify_allowed_lender()?;
 
-        //Set the repayment_time to the current unix timestamp.
-        self.update_repayment_time()?;
+        //Verify the provided USDC mint matches the one in the configuration.
+        self.verify_usdc_mint()?;
 
         //Transfer USDC from the lender's token account to the borrower's token account.
         self.transfer_usdc_funds(self.loan_request.loan_amount)?;
 
+        //Mint the position NFT to the lender, whoever holds it is paid from now on.
+        self.mint_position_nft(loan_request_bump)?;
+
         Ok(())
     }
 
-    /// Verifies that the loan request has not yet been funded.
-    fn verify_not_funded(&self) -> Result<()> {
-        // loan_request.lender must be None before funding.
-        require!(self.loan_request.lender.is_none(), ErrorCode::AlreadyFunded);
-        Ok(())
+    /// Verifies that the lender is on the allowlist of a private loan request.
+    fn verify_allowed_lender(&self) -> Result<()> {
+        check_allowed_lender(&self.loan_request.allowed_lenders, self.lender.key())
     }
 
     /// Verifies that the USDC mint provided in the instruction matches the configuration.
@@ -100,31 +125,27 @@ impl<'info> FundLoan<'info> {
         Ok(())
     }
 
-    /// Updates the loan request state by storing the lender's public key.
-    fn update_loan_request_with_lender(&mut self) -> Result<()> {
-        self.loan_request.lender = Some(self.lender.key());
-        Ok(())
-    }
-
-    /// Updates the repayment_time field with the current Unix timestamp.
-    fn update_repayment_time(&mut self) -> Result<()> {
-        let repayment_time = calculate_repayment_time(self.loan_request.duration_days)?;
-        self.loan_request.repayment_time = Some(repayment_time);
-        Ok(())
-    }
-
     /// Transfers USDC from the lender's associated token account to the borrower's associated token account.
     /// The amount transferred equals the loan amount specified in the loan request.
     fn transfer_usdc_funds(&self, amount: u64) -> Result<()> {
-        let cpi_accounts = Transfer {
-            from: self.lender_usdc_account.to_account_info(),
-            to: self.borrower_usdc_account.to_account_info(),
-            authority: self.lender.to_account_info(),
-        };
-
-        let cpi_program = self.token_program.to_account_info();
+        transfer_loan_funds(
+            &self.token_program.to_account_info(),
+            &self.lender_usdc_account.to_account_info(),
+            &self.borrower_usdc_account.to_account_info(),
+            &self.lender.to_account_info(),
+            &[],
+            amount,
+        )
+    }
 
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-        transfer(cpi_ctx, amount)
+    /// Mints the position NFT to the lender, the mint is owned by the loan request PDA.
+    fn mint_position_nft(&mut self, loan_request_bump: u8) -> Result<()> {
+        mint_position_nft(
+            &self.token_program.to_account_info(),
+            &self.position_mint.to_account_info(),
+            &self.lender_position_token_account.to_account_info(),
+            &mut self.loan_request,
+            loan_request_bump,
+        )
     }
 }
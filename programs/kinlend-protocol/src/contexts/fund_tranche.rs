@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+
+use crate::constants::MAX_TRANCHES;
+use crate::helpers::{
+    calculate_repayment_time, check_allowed_lender, check_usdc_mint_address, close_tranche_escrow,
+    transfer_from_tranche_escrow, transition_loan_status,
+};
+use crate::state::{ConfigState, LenderPositionState, LoanRequestState, LoanStatus};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct FundTranche<'info> {
+    /// The lender putting up a tranche of the loan. Must sign the transaction.
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// Configuration account storing protocol settings.
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    /// Loan Request account representing the loan.
+    /// It also owns the tranche escrow and signs the release of the funds.
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    /// Borrower's main account, receives the USDC once the loan is fully subscribed.
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    /// Position of the lender in the loan, one per lender.
+    #[account(
+        init_if_needed,
+        payer = lender,
+        space = 8 + LenderPositionState::INIT_SPACE,
+        seeds = [b"lender_position", loan_request.key().as_ref(), lender.key().as_ref()],
+        bump
+    )]
+    pub lender_position: Box<Account<'info, LenderPositionState>>,
+
+    /// Escrow holding the tranches until the loan is fully subscribed.
+    #[account(
+        init_if_needed,
+        payer = lender,
+        token::mint = usdc_mint,
+        token::authority = loan_request,
+        seeds = [b"tranche_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    pub tranche_escrow: Box<Account<'info, TokenAccount>>,
+
+    /// Lender's USDC token account.
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = lender_usdc_account.owner == lender.key()
+    )]
+    pub lender_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Borrower's USDC associated token account.
+    #[account(
+        init_if_needed,
+        payer = lender,
+        associated_token::mint = usdc_mint,
+        associated_token::authority = borrower
+    )]
+    pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// The USDC Mint account.
+    pub usdc_mint: Account<'info, Mint>,
+
+    /// Program for token operations.
+    pub token_program: Program<'info, Token>,
+    /// Program for associated token account operations.
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// System program.
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FundTranche<'info> {
+    /// Puts `amount` of USDC into the escrow of the loan and records it on the lender's position.
+    /// The tranche that completes the loan releases the whole escrow to the borrower and starts the loan.
+    pub fn fund_tranche(&mut self, amount: u64, bumps: &FundTrancheBumps) -> Result<()> {
+        // Tranches can only be added while the loan is still requested.
+        require!(self.loan_request.status == LoanStatus::Requested, ErrorCode::AlreadyFunded);
+
+        // A private request can only be funded by one of its allowed lenders.
+        check_allowed_lender(&self.loan_request.allowed_lenders, self.lender.key())?;
+
+        // Verify the provided USDC mint matches the one in the configuration.
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+
+        // The tranche can't be larger than what is left to subscribe.
+        let remaining = self.loan_request.loan_amount
+            .checked_sub(self.loan_request.subscribed_amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(amount > 0 && amount <= remaining, ErrorCode::InvalidAmount);
+
+        self.record_tranche(amount, bumps.lender_position)?;
+        self.transfer_to_escrow(amount)?;
+
+        if amount == remaining {
+            self.release_escrow(bumps.loan_request)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the tranche to the lender's position, opening it on the lender's first tranche.
+    /// A settled position left over from an earlier loan under the same id is opened again.
+    fn record_tranche(&mut self, amount: u64, position_bump: u8) -> Result<()> {
+        if self.lender_position.amount == 0 || self.lender_position.settled {
+            require!(self.loan_request.tranche_count < MAX_TRANCHES, ErrorCode::TooManyTranches);
+
+            self.lender_position.set_inner(LenderPositionState {
+                loan_request: self.loan_request.key(),
+                lender: self.lender.key(),
+                amount: 0,
+                settled: false,
+                bump: position_bump,
+            });
+            self.loan_request.tranche_count = self.loan_request.tranche_count
+                .checked_add(1)
+                .ok_or(ErrorCode::CalculationError)?;
+        }
+
+        self.lender_position.amount = self.lender_position.amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.loan_request.subscribed_amount = self.loan_request.subscribed_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+
+    /// Transfers the tranche from the lender's token account to the escrow.
+    fn transfer_to_escrow(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.lender_usdc_account.to_account_info(),
+            to: self.tranche_escrow.to_account_info(),
+            authority: self.lender.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        transfer(cpi_ctx, amount)
+    }
+
+    /// Marks the fully subscribed loan as funded, sends the whole escrow to the borrower and closes it.
+    /// The escrow's rent goes to the lender completing the loan.
+    fn release_escrow(&mut self, loan_request_bump: u8) -> Result<()> {
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Funded)?;
+        self.loan_request.repayment_time = Some(calculate_repayment_time(self.loan_request.duration_days)?);
+
+        // The balance includes this tranche, and anything sent to the escrow directly
+        self.tranche_escrow.reload()?;
+        let escrow_balance = self.tranche_escrow.amount;
+
+        let token_program = self.token_program.to_account_info();
+        let tranche_escrow = self.tranche_escrow.to_account_info();
+        let loan_request = self.loan_request.to_account_info();
+        let borrower = self.loan_request.borrower;
+        let loan_id = self.loan_request.loan_id;
+
+        transfer_from_tranche_escrow(
+            &token_program,
+            &tranche_escrow,
+            &self.borrower_usdc_account.to_account_info(),
+            &loan_request,
+            borrower,
+            loan_id,
+            loan_request_bump,
+            escrow_balance,
+        )?;
+
+        close_tranche_escrow(
+            &token_program,
+            &tranche_escrow,
+            &self.lender.to_account_info(),
+            &loan_request,
+            borrower,
+            loan_id,
+            loan_request_bump,
+        )
+    }
+}
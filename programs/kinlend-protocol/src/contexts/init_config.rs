@@ -1,7 +1,12 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::Mint;
 
-use crate::state::ConfigState;
+use crate::constants::{
+    DEFAULT_CLAIM_FEE_BPS, DEFAULT_COLLATERAL_RATIO_BPS, DEFAULT_GRACE_PERIOD_DAYS,
+    DEFAULT_LATE_PENALTY_BPS_PER_DAY, DEFAULT_LENDER_FEE_BPS, DEFAULT_LIQUIDATION_FEE_BPS,
+    DEFAULT_LIQUIDATION_THRESHOLD_BPS, DEFAULT_PROTOCOL_FEE_BPS,
+};
+use crate::state::{ConfigState, LoanTerms};
 
 
 #[derive(Accounts)]
@@ -30,6 +35,18 @@ impl<'info> InitConfig<'info> {
     pub fn init_config(&mut self) -> Result<()> {
         self.config.usdc_mint = self.usdc_mint.key();
         self.config.authority = self.admin.key();
+        self.config.treasury = self.admin.key();
+        self.config.forgiveness_fee = 0;
+        self.config.terms = LoanTerms {
+            collateral_ratio_bps: DEFAULT_COLLATERAL_RATIO_BPS,
+            liquidation_threshold_bps: DEFAULT_LIQUIDATION_THRESHOLD_BPS,
+            lender_fee_bps: DEFAULT_LENDER_FEE_BPS,
+            protocol_fee_bps: DEFAULT_PROTOCOL_FEE_BPS,
+            claim_fee_bps: DEFAULT_CLAIM_FEE_BPS,
+            liquidation_fee_bps: DEFAULT_LIQUIDATION_FEE_BPS,
+            grace_period_days: DEFAULT_GRACE_PERIOD_DAYS,
+            late_penalty_bps_per_day: DEFAULT_LATE_PENALTY_BPS_PER_DAY,
+        };
 
         Ok(())
     }
@@ -1,8 +1,16 @@
 use anchor_lang::prelude::*;
-use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token::{Token, TokenAccount};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
+use crate::helpers::{
+    calculate_lender_claim, calculate_outstanding_lender_due, get_collateral_price, get_loan_lender, get_token_vault_collateral,
+    get_vault_collateral, load_tranches, pay_seized_collateral, remove_from_loan_registry, settle_tranches, transition_loan_status,
+    SeizedCollateral, Tranche,
+};
+use crate::math::{is_below_threshold, split_seized_collateral, OraclePrice};
 use crate::state::{
-    CollateralVaultState, LoanRegistryState, LoanRequestState, ProtocolVaultState,
+    CollateralMintState, CollateralVaultState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus,
+    ProtocolVaultState,
 };
 use crate::errors::ErrorCode;
 
@@ -13,21 +21,28 @@ pub struct LiquidateLoan<'info> {
     #[account(mut)]
     pub lender: Signer<'info>,
 
+    /// Borrower of the loan, receives any collateral left after the lender and the protocol are paid.
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
     /// Loan Request account (PDA) that holds the loan details.
-    /// When closed, all lamports (including its rent deposit) are sent to the lender.
+    /// It is kept open so that its `Liquidated` status stays visible on-chain, as repaid, defaulted and forgiven loans are:
+    /// lender positions and position NFTs still point at it. Only a request cancelled before funding is closed.
     #[account(
         mut,
-        close = lender,
         seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
         bump
     )]
     pub loan_request: Box<Account<'info, LoanRequestState>>,
 
-    // Collateral Vault account (PDA) holding the collateral (in SOL).
-    // It is marked with `close = lender` so that any lamports left after fee transfer are returned to the lender.
+    // Collateral Vault account (PDA) holding SOL collateral, and owning the token vault of SPL collateral.
+    // It is marked with `close = borrower` so that any lamports left after the lender and fee transfers are returned to the borrower.
     #[account(
         mut,
-        close = lender,
+        close = borrower,
         seeds = [b"collateral_vault", loan_request.key().as_ref()],
         bump = collateral_vault.bump
     )]
@@ -41,6 +56,15 @@ pub struct LiquidateLoan<'info> {
     )]
     pub loan_registry: Box<Account<'info, LoanRegistryState>>,
 
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
     /// Protocol Vault account (for protocol fees).
     #[account(
         mut,
@@ -49,112 +73,171 @@ pub struct LiquidateLoan<'info> {
     )]
     pub protocol_vault: Box<Account<'info, ProtocolVaultState>>,
 
+    /// Pyth pull-oracle price update for the collateral feed, used to value the collateral.
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    /// Whitelisted mint config pricing SPL collateral. Only for loans backed by a whitelisted mint.
+    pub collateral_mint_config: Option<Box<Account<'info, CollateralMintState>>>,
+
+    /// Token vault holding SPL collateral.
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Lender's token account receiving the lender's share of SPL collateral.
+    /// A syndicated loan pays its tranche lenders from the remaining accounts instead.
+    #[account(
+        mut,
+        constraint = Some(lender_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = lender_collateral_account.owner == lender.key()
+    )]
+    pub lender_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Token account holding the position NFT of the loan, only for loans funded with `fund_loan`.
+    /// Its owner is the lender owed the loan.
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Borrower's token account receiving the surplus SPL collateral.
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    /// Protocol token account collecting the liquidation fee in SPL collateral.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault_token", protocol_collateral_vault.mint.as_ref()],
+        bump,
+        constraint = Some(protocol_collateral_vault.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint
+    )]
+    pub protocol_collateral_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
     /// System Program.
     pub system_program: Program<'info, System>,
-
-    /// Rent sysvar (needed to compute the rent‑exempt minimum).
-    pub rent: Sysvar<'info, Rent>,
 }
 
 impl<'info> LiquidateLoan<'info> {
-    pub fn liquidate_loan(
-        &mut self,
-        sol_price: u64, // SOL price in USDC smallest unit (passed directly)
-    ) -> Result<()> {
-        //  Ensure the lender calling this is the one recorded in the loan request.
-        self.check_lender()?;
-        //  Ensure that liquidation is eligible (i.e. collateral's USD value is below 110% threshold).
-        self.ensure_liquidate_eligible(sol_price)?;
-        //  Calculate net distribution amounts from the collateral vault.
-        //  We subtract the rent‑exempt minimum so that only the "excess" collateral is split.
-        let (_lender_net, protocol_fee) = self.calculate_distribution()?;
-        // Transfer the protocol fee from the collateral vault (PDA) to the protocol vault account.
-        self.transfer_fee(protocol_fee)?;
-        //    the end of the instruction, both the loan_request and collateral_vault accounts
-        //    are closed and their remaining lamports (including rent deposits) are sent to the lender.
+    pub fn liquidate_loan(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        //  Only a funded loan can be liquidated.
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Liquidated)?;
+        //  Tranche lenders of a syndicated loan, as (lender position, destination) pairs in the remaining accounts.
+        let mut tranches = load_tranches(
+            self.loan_request.key(),
+            self.loan_request.tranche_count,
+            remaining_accounts,
+            self.loan_request.collateral_mint,
+        )?;
+        //  Ensure the lender calling this is the one recorded in the loan request, or one of its tranche lenders.
+        self.check_lender(&tranches)?;
+        //  Price the collateral: the haircut price decides eligibility, the market price sizes the lender's share.
+        let (price, haircut_price, decimals) = get_collateral_price(
+            &self.loan_request,
+            self.collateral_mint_config.as_deref().map(|config| &**config),
+            &self.price_update,
+        )?;
+        //  The collateral recorded on the loan, the vault's rent deposit is tracked apart and never split.
+        let collateral = self.get_collateral()?;
+        //  Ensure that liquidation is eligible (i.e. collateral's USD value is below the liquidation threshold).
+        self.ensure_liquidate_eligible(collateral, decimals, &haircut_price)?;
+        //  Calculate net distribution amounts.
+        let (lender_net, protocol_fee) = self.calculate_distribution(collateral, decimals, &price)?;
+        //  Transfer the lender's share and the protocol fee, SPL collateral leaves the token vault, which is closed.
+        pay_seized_collateral(self.seized_collateral(), lender_net, protocol_fee, &tranches)?;
+        settle_tranches(&mut tranches)?;
+        //    At the end of the instruction, the collateral_vault account is closed and its remaining lamports
+        //    (surplus collateral and rent deposit) are sent to the borrower. The loan_request keeps its Liquidated status.
 
         self.remove_from_loan_registry()?;
         Ok(())
     }
 
-    /// Verifies that the lender in the loan request matches the signer.
-    fn check_lender(&self) -> Result<()> {
-        require!(
-            self.loan_request.lender == Some(self.lender.key()),
-            ErrorCode::NotRightLender
-        );
+    /// Verifies that the signer is the lender in the loan request or the holder of its position NFT,
+    /// or that the signer holds one of the positions of a syndicated loan.
+    fn check_lender(&self, tranches: &[Tranche<'info>]) -> Result<()> {
+        let lender = self.lender.key();
+        if tranches.is_empty() {
+            require!(self.get_loan_lender() == Some(lender), ErrorCode::NotRightLender);
+        } else {
+            require!(
+                tranches.iter().any(|tranche| tranche.position.lender == lender),
+                ErrorCode::NotRightLender
+            );
+        }
         Ok(())
     }
 
+    /// Lender owed the loan, the holder of its position NFT when it has one.
+    fn get_loan_lender(&self) -> Option<Pubkey> {
+        get_loan_lender(&self.loan_request, self.position_token_account.as_deref().map(|account| &**account))
+    }
+
+    /// Retrieves the collateral recorded on the loan after checking its vault still holds it.
+    fn get_collateral(&self) -> Result<u64> {
+        if self.loan_request.collateral_mint.is_some() {
+            let token_vault = self.collateral_token_vault.as_ref().ok_or(ErrorCode::MissingCollateralAccount)?;
+            return get_token_vault_collateral(token_vault.amount, self.loan_request.collateral);
+        }
+        get_vault_collateral(&self.collateral_vault.to_account_info(), self.loan_request.collateral)
+    }
+
     /// Checks if the loan is eligible for liquidation.
-    /// Liquidation is allowed if the USD value of the collateral is below 110% of the loan amount.
-    fn ensure_liquidate_eligible(&mut self, sol_price: u64) -> Result<()> {
-        let collateral_lamports = self.collateral_vault.to_account_info().lamports();
-        // Convert lamports to SOL (1 SOL = 1e9 lamports) and then to USD.
-        let collateral_usd_value = (collateral_lamports as f64 / 1e9) * (sol_price as f64);
-        // Assume loan_request.loan_amount is expressed in USD.
-        let liquidation_threshold = self.loan_request.loan_amount as f64 * 1.10;
-        require!(
-            collateral_usd_value < liquidation_threshold,
-            ErrorCode::CannotLiquidateYet
-        );
+    /// Liquidation is allowed if the USD value of the collateral is below the loan's liquidation threshold.
+    /// The price is read from the oracle, a stale or mismatched feed is rejected.
+    fn ensure_liquidate_eligible(&self, collateral: u64, decimals: u32, price: &OraclePrice) -> Result<()> {
+        // The outstanding principal is expressed in USDC smallest unit, compared exactly against the threshold.
+        let below_threshold = is_below_threshold(
+            collateral,
+            decimals,
+            price,
+            self.loan_request.outstanding_principal,
+            self.loan_request.terms.liquidation_threshold_bps as u64,
+        )?;
+        require!(below_threshold, ErrorCode::CannotLiquidateYet);
         Ok(())
     }
 
-    
-    fn calculate_distribution(&mut self) -> Result<(u64, u64)> {
-        let collateral_info = self.collateral_vault.to_account_info();
-        let total_collateral = collateral_info.lamports();
-        let rent_exempt = self.rent.minimum_balance(collateral_info.data_len());
-        require!(
-            total_collateral > rent_exempt,
-            ErrorCode::InsuffientCollateral
-        );
-        let collateral_net = total_collateral - rent_exempt;
-        let protocol_fee = collateral_net
-            .checked_mul(2)
-            .and_then(|x| x.checked_div(110))
-            .ok_or(ErrorCode::CalculationError)?;
-        let lender_net = collateral_net
-            .checked_sub(protocol_fee)
-            .ok_or(ErrorCode::CalculationError)?;
+    /// Splits the collateral recorded on the loan:
+    /// the lender gets collateral worth the outstanding principal plus lender fee or interest, the protocol takes the
+    /// liquidation fee on that share and whatever is left goes back to the borrower.
+    fn calculate_distribution(&self, collateral: u64, decimals: u32, price: &OraclePrice) -> Result<(u64, u64)> {
+        let lender_due = calculate_outstanding_lender_due(&self.loan_request)?;
+        let lender_claim = calculate_lender_claim(lender_due, decimals, price)?;
+        let (lender_net, protocol_fee, _borrower_net) = split_seized_collateral(
+            collateral,
+            lender_claim,
+            self.loan_request.terms.liquidation_fee_bps as u64,
+        )?;
         Ok((lender_net, protocol_fee))
     }
 
-    /// Transfers the protocol fee from the collateral vault (a PDA) to the protocol vault account.
-    /// We use Anchor's `CpiContext::new_with_signer` to supply the PDA's seeds.
-    fn transfer_fee(&mut self, fee: u64) -> Result<()> {
-
-         // Get account info references
-        let from_info = self.collateral_vault.to_account_info();
-        let to_info = self.protocol_vault.to_account_info();
-        
-        // Transfer lamports directly between account infos
-        **from_info.try_borrow_mut_lamports()? = from_info.lamports().checked_sub(fee).ok_or(ErrorCode::CalculationError)?;
-        **to_info.try_borrow_mut_lamports()? = to_info.lamports().checked_add(fee).ok_or(ErrorCode::CalculationError)?;
-        
-        Ok(())
+    /// Accounts paying out the seized collateral.
+    fn seized_collateral(&self) -> SeizedCollateral<'_, 'info> {
+        SeizedCollateral {
+            loan_request: &self.loan_request,
+            collateral_vault: &self.collateral_vault,
+            lender: self.lender.as_ref(),
+            borrower: self.borrower.as_ref(),
+            protocol_vault: self.protocol_vault.as_ref().as_ref(),
+            collateral_token_vault: self.collateral_token_vault.as_deref(),
+            lender_collateral_account: self.lender_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+            borrower_collateral_account: self.borrower_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+            protocol_collateral_vault: self.protocol_collateral_vault.as_ref().map(|account| account.as_ref().as_ref()),
+            token_program: self.token_program.as_ref().map(|program| program.as_ref()),
+        }
     }
 
 
 
-    // remove the loan request from the loan registry
+    // remove the loan request from its loan registry page
     fn remove_from_loan_registry(&mut self) -> Result<()> {
         let loan_request_key = self.loan_request.key();
-        
-        // Find the index of the loan request in the registry
-        let position = self.loan_registry.loan_requests.iter()
-            .position(|&pubkey| pubkey == loan_request_key)
-            .ok_or(ErrorCode::NotFoundInRegistry)?;
-        
-        // Remove the loan request from the registry
-        self.loan_registry.loan_requests.remove(position);
-        
-        // Decrement the total loans counter
-        self.loan_registry.total_loans = self.loan_registry.total_loans
-            .checked_sub(1)
-            .ok_or(ErrorCode::CalculationError)?;
-            
-        Ok(())
+        remove_from_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
     }
 }
@@ -0,0 +1,172 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::constants::SOL_DECIMALS;
+use crate::helpers::{
+    calculate_lender_claim, calculate_outstanding_lender_due, get_sol_price, get_vault_collateral, remove_from_loan_registry,
+    seize_pool_collateral, settle_pool_loan_usdc, transition_loan_status,
+};
+use crate::math::{is_below_threshold, split_seized_collateral};
+use crate::state::{
+    CollateralVaultState, LendingPoolState, LoanRegistryPageState, LoanRegistryState, LoanRequestState, LoanStatus,
+    PoolLoanState, ProtocolVaultState,
+};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct LiquidatePoolLoan<'info> {
+    /// Anyone can liquidate a pool loan below its liquidation threshold on behalf of the pool.
+    pub keeper: Signer<'info>,
+
+    /// Borrower of the loan, receives any collateral left after the pool and the protocol are paid.
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    /// Loan Request account funded by the pool.
+    /// It is kept open so that its `Liquidated` status stays visible on-chain.
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump,
+        constraint = loan_request.lender == Some(pool_loan.key()) @ ErrorCode::NotRightLender
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    /// Collateral Vault holding the SOL collateral, the surplus and the rent go back to the borrower on close.
+    #[account(
+        mut,
+        close = borrower,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    /// Lending pool receiving the lender's share of the collateral.
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Pool loan of the liquidated loan, closed once its principal is written off the pool.
+    #[account(
+        mut,
+        close = rent_receiver,
+        seeds = [b"pool_loan", loan_request.key().as_ref()],
+        bump = pool_loan.bump
+    )]
+    pub pool_loan: Box<Account<'info, PoolLoanState>>,
+
+    /// USDC account of the pool loan, holding any partial repayment not settled yet.
+    #[account(
+        mut,
+        seeds = [b"pool_loan_usdc", loan_request.key().as_ref()],
+        bump
+    )]
+    pub pool_loan_usdc: Box<Account<'info, TokenAccount>>,
+
+    /// Keeper who funded the loan, gets the rent of the pool loan accounts back.
+    /// CHECK: only receives lamports, checked against the pool loan.
+    #[account(
+        mut,
+        address = pool_loan.keeper
+    )]
+    pub rent_receiver: AccountInfo<'info>,
+
+    /// Protocol Vault account (PDA) that collects fees.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump
+    )]
+    pub protocol_vault: Box<Account<'info, ProtocolVaultState>>,
+
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump,
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    /// Pyth pull-oracle price update for SOL/USD, decides eligibility and sizes the pool's share.
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> LiquidatePoolLoan<'info> {
+    /// Liquidates a pool loan whose collateral fell below the liquidation threshold, with the same split as
+    /// `liquidate_loan`: collateral worth the outstanding principal plus lender fee or interest goes to the pool,
+    /// the liquidation fee to the protocol vault and the surplus back to the borrower.
+    /// The loan is settled at once, like a defaulted pool loan in `claim_pool_collateral`.
+    pub fn liquidate_pool_loan(&mut self) -> Result<()> {
+        // Only a funded loan can be liquidated.
+        transition_loan_status(&mut self.loan_request.status, LoanStatus::Liquidated)?;
+
+        let collateral = get_vault_collateral(&self.collateral_vault.to_account_info(), self.loan_request.collateral)?;
+        let sol_price = get_sol_price(&self.price_update)?;
+        let below_threshold = is_below_threshold(
+            collateral,
+            SOL_DECIMALS,
+            &sol_price,
+            self.loan_request.outstanding_principal,
+            self.loan_request.terms.liquidation_threshold_bps as u64,
+        )?;
+        require!(below_threshold, ErrorCode::CannotLiquidateYet);
+
+        let lender_due = calculate_outstanding_lender_due(&self.loan_request)?;
+        let lender_claim = calculate_lender_claim(lender_due, SOL_DECIMALS, &sol_price)?;
+        let (pool_amount, fee, _borrower_amount) = split_seized_collateral(
+            collateral,
+            lender_claim,
+            self.loan_request.terms.liquidation_fee_bps as u64,
+        )?;
+
+        seize_pool_collateral(
+            &self.collateral_vault.to_account_info(),
+            &mut self.lending_pool,
+            &self.protocol_vault.to_account_info(),
+            pool_amount,
+            fee,
+        )?;
+
+        // Any partial repayment moves into the pool vault and the unpaid principal is written off the pool.
+        let unpaid_principal = self.loan_request.outstanding_principal;
+        settle_pool_loan_usdc(
+            &self.token_program.to_account_info(),
+            &self.pool_loan_usdc,
+            &self.pool_usdc_vault.to_account_info(),
+            &mut self.pool_loan,
+            &mut self.lending_pool,
+            unpaid_principal,
+            Some(&self.rent_receiver.to_account_info()),
+        )?;
+
+        let loan_request_key = self.loan_request.key();
+        remove_from_loan_registry(&mut self.loan_registry, &mut self.loan_registry_page, loan_request_key)
+    }
+}
@@ -6,8 +6,40 @@ pub mod claim_collateral;
 pub mod cancel_loan_request;
 pub mod liquidate_loan;
 pub mod create_loan_registry;
+pub mod create_loan_registry_page;
 pub mod init_config;
 pub mod update_config;
+pub mod withdraw_protocol_fees;
+pub mod withdraw_protocol_fees_usdc;
+pub mod create_loan_request_token;
+pub mod whitelist_collateral_mint;
+pub mod update_collateral_mint;
+pub mod withdraw_protocol_fees_token;
+pub mod add_collateral;
+pub mod withdraw_excess_collateral;
+pub mod repay_partial;
+pub mod propose_loan_extension;
+pub mod accept_loan_extension;
+pub mod forgive_loan;
+pub mod repay_on_behalf;
+pub mod fund_tranche;
+pub mod withdraw_tranche;
+pub mod close_lender_position;
+pub mod create_offer_registry;
+pub mod create_offer_registry_page;
+pub mod create_loan_offer;
+pub mod accept_loan_offer;
+pub mod cancel_loan_offer;
+pub mod create_lending_pool;
+pub mod update_pool_policy;
+pub mod deposit_to_pool;
+pub mod redeem_from_pool;
+pub mod fund_from_pool;
+pub mod settle_pool_loan;
+pub mod claim_pool_collateral;
+pub mod buy_pool_collateral;
+pub mod refinance_loan;
+pub mod liquidate_pool_loan;
 
 pub use create_loan_request::*;
 pub use create_protocol_vault::*;
@@ -17,5 +49,37 @@ pub use claim_collateral::*;
 pub use cancel_loan_request::*;
 pub use liquidate_loan::*;
 pub use create_loan_registry::*;
+pub use create_loan_registry_page::*;
 pub use init_config::*;
-pub use update_config::*;
\ No newline at end of file
+pub use update_config::*;
+pub use withdraw_protocol_fees::*;
+pub use withdraw_protocol_fees_usdc::*;
+pub use create_loan_request_token::*;
+pub use whitelist_collateral_mint::*;
+pub use update_collateral_mint::*;
+pub use withdraw_protocol_fees_token::*;
+pub use add_collateral::*;
+pub use withdraw_excess_collateral::*;
+pub use repay_partial::*;
+pub use propose_loan_extension::*;
+pub use accept_loan_extension::*;
+pub use forgive_loan::*;
+pub use repay_on_behalf::*;
+pub use fund_tranche::*;
+pub use withdraw_tranche::*;
+pub use close_lender_position::*;
+pub use create_offer_registry::*;
+pub use create_offer_registry_page::*;
+pub use create_loan_offer::*;
+pub use accept_loan_offer::*;
+pub use cancel_loan_offer::*;
+pub use create_lending_pool::*;
+pub use update_pool_policy::*;
+pub use deposit_to_pool::*;
+pub use redeem_from_pool::*;
+pub use fund_from_pool::*;
+pub use settle_pool_loan::*;
+pub use claim_pool_collateral::*;
+pub use buy_pool_collateral::*;
+pub use refinance_loan::*;
+pub use liquidate_pool_loan::*;
\ No newline at end of file
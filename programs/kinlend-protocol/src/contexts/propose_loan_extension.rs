@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::helpers::{calculate_grace_deadline, check_deadline_is_not_expired};
+use crate::state::{LoanExtension, LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct ProposeLoanExtension<'info> {
+
+    //only the borrower can propose an extension of his/her loan
+    pub borrower: Signer<'info>,
+
+    //Loan Request
+    #[account(
+        mut,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+}
+
+impl<'info> ProposeLoanExtension<'info> {
+
+    /// Proposes a new total duration (counted from funding) and an optional fee for the lender.
+    /// A new proposal replaces any pending one, it only applies once the lender accepts it.
+    /// Syndicated loans have no single lender to accept it, so they can't be extended.
+    pub fn propose_loan_extension(&mut self, duration_days: u64, fee: Option<u64>) -> Result<()> {
+
+        //only a funded loan can be extended, and only before it can be claimed
+        require!(self.loan_request.status == LoanStatus::Funded, ErrorCode::NotFunded);
+        check_deadline_is_not_expired(calculate_grace_deadline(&self.loan_request)?)?;
+
+        require!(self.loan_request.tranche_count == 0, ErrorCode::SyndicatedLoanNotSupported);
+
+        //the new duration must push the deadline back
+        require!(duration_days > self.loan_request.duration_days, ErrorCode::InvalidExtension);
+
+        self.loan_request.pending_extension = Some(LoanExtension {
+            duration_days,
+            fee: fee.unwrap_or(0),
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{burn, Burn, Mint, Token, TokenAccount};
+
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::helpers::{calculate_pool_value, get_sol_price, transfer_loan_funds};
+use crate::math::assets_for_shares;
+use crate::state::LendingPoolState;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct RedeemFromPool<'info> {
+    // Share holder redeeming
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    // Pool vault paying out the redemption
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_share_mint"],
+        bump
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    // Owner's token account holding the shares to burn
+    #[account(
+        mut,
+        constraint = owner_share_account.mint == share_mint.key(),
+        constraint = owner_share_account.owner == owner.key()
+    )]
+    pub owner_share_account: Box<Account<'info, TokenAccount>>,
+
+    // Owner's USDC token account receiving the redemption
+    #[account(
+        mut,
+        constraint = owner_usdc_account.mint == pool_usdc_vault.mint,
+        constraint = owner_usdc_account.owner == owner.key()
+    )]
+    pub owner_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    // Pyth pull-oracle price update for SOL/USD, values the SOL seized by the pool
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> RedeemFromPool<'info> {
+    /// Burns `shares` and pays their value in USDC from the pool vault.
+    /// Only the USDC not lent out can be redeemed, redemptions above it fail until loans are repaid.
+    pub fn redeem_from_pool(&mut self, shares: u64) -> Result<()> {
+        require!(shares > 0, ErrorCode::InvalidAmount);
+
+        let sol_price = get_sol_price(&self.price_update)?;
+        let liquidity = self.pool_usdc_vault.amount;
+        let pool_value = calculate_pool_value(&self.lending_pool, liquidity, &sol_price)?;
+        let amount = assets_for_shares(shares, self.share_mint.supply, pool_value)?;
+        require!(amount > 0, ErrorCode::PoolAmountTooSmall);
+        require!(amount <= liquidity, ErrorCode::InsufficientPoolLiquidity);
+
+        let cpi_accounts = Burn {
+            mint: self.share_mint.to_account_info(),
+            from: self.owner_share_account.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+        burn(cpi_ctx, shares)?;
+
+        let pool_seeds: &[&[u8]] = &[b"lending_pool", &[self.lending_pool.bump]];
+        transfer_loan_funds(
+            &self.token_program.to_account_info(),
+            &self.pool_usdc_vault.to_account_info(),
+            &self.owner_usdc_account.to_account_info(),
+            &self.lending_pool.to_account_info(),
+            &[pool_seeds],
+            amount,
+        )
+    }
+}
@@ -0,0 +1,242 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::helpers::{
+    calculate_full_repayment_amounts, calculate_grace_deadline, calculate_repayment_time, check_apr_bps, check_balance,
+    check_deadline_is_not_expired, check_right_borrower, check_usdc_mint_address, get_collateral_price, is_loan_lender_account,
+    load_tranches, mint_position_nft, record_pool_loan_earnings, settle_tranches, transfer_repayment,
+};
+use crate::math::required_collateral;
+use crate::state::{CollateralMintState, ConfigState, LendingPoolState, LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct RefinanceLoan<'info> {
+
+    //borrower agreeing to the new terms
+    pub borrower: Signer<'info>,
+
+    //new lender paying off the current lender
+    #[account(mut)]
+    pub new_lender: Signer<'info>,
+
+    //new lender's USDC account supplying the payoff
+    #[account(
+        mut,
+        constraint = new_lender_usdc_account.mint == usdc_mint.key(),
+        constraint = new_lender_usdc_account.owner == new_lender.key()
+    )]
+    pub new_lender_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    //current lender's USDC ATA, must belong to the lender recorded in the loan request or the holder of its position NFT,
+    //or be the pool loan's USDC account for a loan funded by the lending pool
+    //a syndicated loan pays off its tranche lenders from the remaining accounts instead
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = is_loan_lender_account(&loan_request, lender_usdc_account, position_token_account.as_deref().map(|account| &**account)) @ ErrorCode::NotRightLender
+    )]
+    pub lender_usdc_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //token account holding the position NFT of the loan, only for loans funded with fund_loan
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //Loan Request account, re-pointed to the new lender
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //new position NFT of the loan, minted to the new lender, any previous position NFT no longer applies
+    #[account(
+        init,
+        payer = new_lender,
+        mint::decimals = 0,
+        mint::authority = loan_request,
+        seeds = [b"position_mint", loan_request.key().as_ref(), &loan_request.position_count.to_le_bytes()],
+        bump
+    )]
+    pub position_mint: Box<Account<'info, Mint>>,
+
+    //new lender's associated token account receiving the position NFT
+    #[account(
+        init,
+        payer = new_lender,
+        associated_token::mint = position_mint,
+        associated_token::authority = new_lender
+    )]
+    pub new_lender_position_token_account: Box<Account<'info, TokenAccount>>,
+
+    //protocol vault USDC
+    #[account(
+        init_if_needed,
+        payer = new_lender,
+        token::mint = usdc_mint,
+        token::authority = protocol_vault_authority,
+        seeds = [b"protocol_vault_usdc"],
+        bump
+    )]
+    pub protocol_vault_usdc: Box<Account<'info, TokenAccount>>,
+
+    //authority for protocol_vault_usdc
+    ///CHECK: only used as authority for protocol_vault_usdc
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    //config account, its current terms replace the ones snapshotted on the loan
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    //Pyth pull-oracle price update for the collateral feed, the collateral must cover the new principal
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    //whitelisted mint config pricing SPL collateral, only for loans backed by a whitelisted mint
+    pub collateral_mint_config: Option<Box<Account<'info, CollateralMintState>>>,
+
+    //lending pool, only for loans funded by the lending pool, counts the lender fee or interest in the payoff of the pool loan
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Option<Box<Account<'info, LendingPoolState>>>,
+
+    //USDC_mint
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+impl<'info> RefinanceLoan<'info> {
+
+    /// Pays off the current lender of a funded loan with the new lender's USDC and re-points the loan to the new lender.
+    /// The new lender supplies the full payoff, which becomes the new principal under the current config terms and
+    /// the duration and APR agreed by the borrower. The collateral stays in its vault, and the new lender receives
+    /// a new position NFT of the loan: the previous one, if any, no longer entitles its holder to anything.
+    pub fn refinance_loan(
+        &mut self,
+        duration_days: u64,
+        apr_bps: Option<u16>,
+        loan_request_bump: u8,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+
+        //only the borrower of the loan can agree to new terms
+        check_right_borrower(self.borrower.key(), self.loan_request.borrower)?;
+
+        //only a funded loan can be refinanced, until the end of its grace window
+        require!(self.loan_request.status == LoanStatus::Funded, ErrorCode::NotFunded);
+        let grace_deadline = calculate_grace_deadline(&self.loan_request)?;
+        check_deadline_is_not_expired(grace_deadline)?;
+
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+        check_apr_bps(apr_bps)?;
+        require!(duration_days > 0, ErrorCode::InvalidDuration);
+
+        //payoff of the current loan, exactly what repay_loan would charge now
+        let (lender_amount, fee, payoff) = calculate_full_repayment_amounts(&self.loan_request)?;
+        check_balance(self.new_lender_usdc_account.amount, payoff)?;
+
+        //the collateral must cover the payoff at the current collateral ratio
+        self.verify_collateral(payoff)?;
+
+        //tranche lenders of a syndicated loan, as (position, lender USDC account) pairs
+        let mut tranches = load_tranches(
+            self.loan_request.key(),
+            self.loan_request.tranche_count,
+            remaining_accounts,
+            Some(self.usdc_mint.key()),
+        )?;
+
+        // Transfer USDC tokens from the new lender:
+        //    - what the current lender is owed goes to the lender, or pro rata to the tranche lenders.
+        //    - protocol fee goes to the protocol vault.
+        transfer_repayment(
+            &self.token_program.to_account_info(),
+            &self.new_lender_usdc_account.to_account_info(),
+            &self.new_lender.to_account_info(),
+            self.lender_usdc_account.as_ref().map(|account| account.as_ref().as_ref()),
+            &self.protocol_vault_usdc.to_account_info(),
+            &tranches,
+            lender_amount,
+            fee,
+        )?;
+        settle_tranches(&mut tranches)?;
+        record_pool_loan_earnings(
+            &self.loan_request,
+            self.lending_pool.as_deref_mut().map(|pool| &mut **pool),
+            lender_amount,
+            self.loan_request.outstanding_principal,
+        )?;
+
+        self.restart_loan(payoff, duration_days, apr_bps)?;
+
+        //the new lender holds the position of the loan from now on
+        mint_position_nft(
+            &self.token_program.to_account_info(),
+            &self.position_mint.to_account_info(),
+            &self.new_lender_position_token_account.to_account_info(),
+            &mut self.loan_request,
+            loan_request_bump,
+        )
+    }
+
+
+    /// Verifies that the collateral recorded on the loan is worth at least the collateral ratio of the new principal,
+    /// at the haircut price for SPL collateral.
+    fn verify_collateral(&self, principal: u64) -> Result<()> {
+        let (_price, haircut_price, decimals) = get_collateral_price(
+            &self.loan_request,
+            self.collateral_mint_config.as_deref().map(|config| &**config),
+            &self.price_update,
+        )?;
+        let required = required_collateral(
+            principal,
+            self.config.terms.collateral_ratio_bps as u64,
+            decimals,
+            &haircut_price,
+        )?;
+        require!(self.loan_request.collateral >= required, ErrorCode::InsuffientCollateral);
+
+        Ok(())
+    }
+
+
+    /// Restarts the loan in the new lender's name: the payoff is the new principal, the deadline is counted from now
+    /// and the extension and tranche records of the previous loan are cleared.
+    fn restart_loan(&mut self, principal: u64, duration_days: u64, apr_bps: Option<u16>) -> Result<()> {
+        let loan_request = &mut self.loan_request;
+
+        loan_request.lender = Some(self.new_lender.key());
+        loan_request.loan_amount = principal;
+        loan_request.outstanding_principal = principal;
+        loan_request.apr_bps = apr_bps;
+        loan_request.duration_days = duration_days;
+        loan_request.repayment_time = Some(calculate_repayment_time(duration_days)?);
+        loan_request.terms = self.config.terms;
+        loan_request.pending_extension = None;
+        loan_request.extension_count = 0;
+        loan_request.extension_fees = 0;
+        loan_request.subscribed_amount = 0;
+        loan_request.tranche_count = 0;
+
+        Ok(())
+    }
+}
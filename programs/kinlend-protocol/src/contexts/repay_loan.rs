@@ -1,8 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
-use crate::helpers::{check_balance, check_deadline_is_not_expired, check_right_borrower, check_usdc_mint_address};
-use crate::state::{ CollateralVaultState, ConfigState, LoanRegistryState, LoanRequestState};
+use crate::helpers::{check_right_borrower, is_loan_lender_account, repay_in_full, FullRepayment};
+use crate::state::{ CollateralVaultState, ConfigState, LendingPoolState, LoanRegistryPageState, LoanRegistryState, LoanRequestState};
 
 use crate::errors::ErrorCode;
 
@@ -22,12 +22,18 @@ pub struct RepayLoan<'info> {
     )]
     pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
 
-    //lender's USDC ATA
+    //lender's USDC ATA, must belong to the lender recorded in the loan request or the holder of its position NFT,
+    //or be the pool loan's USDC account for a loan funded by the lending pool
+    //a syndicated loan pays its tranche lenders from the remaining accounts instead
     #[account(
         mut,
-        constraint = lender_usdc_account.mint == usdc_mint.key()
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = is_loan_lender_account(&loan_request, lender_usdc_account, position_token_account.as_deref().map(|account| &**account)) @ ErrorCode::NotRightLender
     )]
-    pub lender_usdc_account: Box<Account<'info, TokenAccount>>,
+    pub lender_usdc_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //token account holding the position NFT of the loan, only for loans funded with fund_loan
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
 
 
     //Loan Request account
@@ -69,7 +75,6 @@ pub struct RepayLoan<'info> {
 
     //config account which stores mint address of usdc
     #[account(
-        mut,
         seeds = [b"config"],
         bump,
     )]
@@ -82,6 +87,39 @@ pub struct RepayLoan<'info> {
     )]
     pub loan_registry: Box<Account<'info, LoanRegistryState>>,
 
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    //lending pool, only for loans funded by the lending pool, counts the lender fee or interest paid to the pool loan
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Option<Box<Account<'info, LendingPoolState>>>,
+
+    //token vault holding SPL collateral, only for loans backed by a whitelisted mint
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account receiving the SPL collateral back
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
     //USDC_mint
     pub usdc_mint: Account<'info, Mint>,
 
@@ -95,121 +133,37 @@ pub struct RepayLoan<'info> {
 
 impl<'info> RepayLoan<'info> {
 
-    pub fn repay_loan(&mut self) -> Result<()> {
+    pub fn repay_loan(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
 
         //can only be repaid by the borrower who has taken loan 
-        let borrower = self.borrower.key();
-        let loan_request_borrower = self.loan_request.borrower;
-        check_right_borrower(borrower, loan_request_borrower)?;
-
-        //checking deadline
-        let repayment_time = self.loan_request.repayment_time.unwrap();
-
-        check_deadline_is_not_expired(repayment_time)?;
-        
-        //checking usdc_mint
-        let config_usdc_mint = self.config.usdc_mint;
-        let usdc_mint = self.usdc_mint.key();
-        check_usdc_mint_address(config_usdc_mint, usdc_mint)?;
-
-        //calculating repayment amount
-        let (lender_amount, fee, total_amount) = self.calculate_repayment_amounts()?;
-
-        //check borrower balance
-        let borrower_usdc_balance = self.borrower_usdc_account.amount;
-        check_balance(borrower_usdc_balance, total_amount)?;
+        check_right_borrower(self.borrower.key(), self.loan_request.borrower)?;
 
         // Transfer USDC tokens:
-        //    - 104% of the loan amount goes to the lender.
-        //    - 1% of the loan amount (as fee) goes to the protocol vault.
-        self.transfer_tokens(lender_amount, fee)?;
-        
+        //    - loan amount plus lender fee goes to the lender, or pro rata to the tranche lenders.
+        //    - protocol fee goes to the protocol vault.
         // When the instruction completes, the collateral_vault account is automatically closed,
         // and its entire lamport balance is transferred to the borrower because of `close = borrower`.
-        self.remove_from_loan_registry()?;
-
-
-        Ok(())
-    }
-
-
-    /// Calculate repayment amounts:
-    /// - lender_amount: 104% of the loan amount (USDC).
-    /// - fee: 1% of the loan amount (USDC).
-    /// - total_amount: Sum of the two (used for balance checks).
-    fn calculate_repayment_amounts(&mut self) -> Result<(u64,u64, u64)> {
-
-        let loan_amount = self.loan_request.loan_amount;
-        let lender_amount = loan_amount
-                        .checked_mul(104)
-                        .and_then(|x| x.checked_div(100))
-                        .ok_or(ErrorCode::CalculationError)?;
-        
-        let fee = loan_amount
-                    .checked_mul(1)
-                    .and_then(|x| x.checked_div(100))
-                    .ok_or(ErrorCode::CalculationError)?;
-        
-        let total_amount = lender_amount.checked_add(fee).ok_or(ErrorCode::CalculationError)?;
-
-        Ok((lender_amount,fee,total_amount))
-    }
-
-
-    fn transfer_tokens(&mut self, lender_amount: u64, fee:u64) -> Result<()> {
-
-        //all needed accounts
-        let cpi_accounts = Transfer{
-            from: self.borrower_usdc_account.to_account_info(),
-            to: self.lender_usdc_account.to_account_info(),
-            authority: self.borrower.to_account_info()
-        };
-
-        //token program for transferring usdc to token
-        let cpi_program = self.token_program.to_account_info();
-
-        //Creating CPI Context for Cross Program Invocation
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-
-        transfer(cpi_ctx, lender_amount)?;
-
-
-        //doing same for transferring usdc to protocol vault usdc account
-        let cpi_accounts = Transfer {
-            from: self.borrower_usdc_account.to_account_info(),
-            to: self.protocol_vault_usdc.to_account_info(),
-            authority: self.borrower.to_account_info(),
-        };
-
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-
-        transfer(cpi_ctx, fee)?;
-
-
-        Ok(())
+        // SPL collateral is returned from the token vault before that.
+        let borrower = self.borrower.to_account_info();
+        repay_in_full(
+            FullRepayment {
+                payer: &borrower,
+                payer_usdc_account: &self.borrower_usdc_account,
+                borrower: &borrower,
+                lender_usdc_account: self.lender_usdc_account.as_ref().map(|account| account.as_ref().as_ref()),
+                loan_request: &mut self.loan_request,
+                collateral_vault: &self.collateral_vault,
+                protocol_vault_usdc: self.protocol_vault_usdc.as_ref().as_ref(),
+                config: &self.config,
+                loan_registry: &mut self.loan_registry,
+                loan_registry_page: &mut self.loan_registry_page,
+                lending_pool: self.lending_pool.as_deref_mut().map(|pool| &mut **pool),
+                collateral_token_vault: self.collateral_token_vault.as_deref(),
+                borrower_collateral_account: self.borrower_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+                usdc_mint: self.usdc_mint.key(),
+                token_program: self.token_program.as_ref(),
+            },
+            remaining_accounts,
+        )
     }
-    
-
-
-    // remove the loan request from the loan registry
-    fn remove_from_loan_registry(&mut self) -> Result<()> {
-        let loan_request_key = self.loan_request.key();
-        
-        // Find the index of the loan request in the registry
-        let position = self.loan_registry.loan_requests.iter()
-            .position(|&pubkey| pubkey == loan_request_key)
-            .ok_or(ErrorCode::NotFoundInRegistry)?;
-        
-        // Remove the loan request from the registry
-        self.loan_registry.loan_requests.remove(position);
-        
-        // Decrement the total loans counter
-        self.loan_registry.total_loans = self.loan_registry.total_loans
-            .checked_sub(1)
-            .ok_or(ErrorCode::CalculationError)?;
-            
-        Ok(())
-    }
-}
\ No newline at end of file
+}
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::helpers::{is_loan_lender_account, repay_in_full, FullRepayment};
+use crate::state::{CollateralVaultState, ConfigState, LendingPoolState, LoanRegistryPageState, LoanRegistryState, LoanRequestState};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct RepayOnBehalf<'info> {
+
+    //anyone repaying the loan for the borrower (parent, guarantor...)
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    //borrower recorded on the loan, the collateral always goes back to this account
+    #[account(
+        mut,
+        address = loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower: SystemAccount<'info>,
+
+    //payer's USDC account supplying the repayment
+    #[account(
+        mut,
+        constraint = payer_usdc_account.mint == usdc_mint.key(),
+        constraint = payer_usdc_account.owner == payer.key()
+    )]
+    pub payer_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    //lender's USDC ATA, must belong to the lender recorded in the loan request or the holder of its position NFT,
+    //or be the pool loan's USDC account for a loan funded by the lending pool
+    //a syndicated loan pays its tranche lenders from the remaining accounts instead
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = is_loan_lender_account(&loan_request, lender_usdc_account, position_token_account.as_deref().map(|account| &**account)) @ ErrorCode::NotRightLender
+    )]
+    pub lender_usdc_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //token account holding the position NFT of the loan, only for loans funded with fund_loan
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //Loan Request account
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //Collateral Vault Account, closed to the borrower
+    #[account(
+        mut,
+        close = borrower,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    //protocol vault USDC
+    #[account(
+        init_if_needed,
+        payer = payer,
+        token::mint = usdc_mint,
+        token::authority = protocol_vault_authority,
+        seeds = [b"protocol_vault_usdc"],
+        bump
+    )]
+    pub protocol_vault_usdc: Box<Account<'info, TokenAccount>>,
+
+    //authority for protocol_vault_usdc
+    ///CHECK: only used as authority for protocol_vault_usdc
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    //config account which stores mint address of usdc
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    #[account(
+        mut,
+        seeds = [b"loan_registry"],
+        bump,
+    )]
+    pub loan_registry: Box<Account<'info, LoanRegistryState>>,
+
+    //registry page listing this loan request
+    #[account(
+        mut,
+        seeds = [b"loan_registry_page", &loan_request.registry_page.to_le_bytes()],
+        bump = loan_registry_page.bump,
+        constraint = loan_registry_page.page_index == loan_request.registry_page @ ErrorCode::LoanRegistryPageNotFound
+    )]
+    pub loan_registry_page: Box<Account<'info, LoanRegistryPageState>>,
+
+    //lending pool, only for loans funded by the lending pool, counts the lender fee or interest paid to the pool loan
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Option<Box<Account<'info, LendingPoolState>>>,
+
+    //token vault holding SPL collateral, only for loans backed by a whitelisted mint
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account receiving the SPL collateral back, never the payer's
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == loan_request.borrower @ ErrorCode::NotRightBorrower
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //USDC_mint
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+impl<'info> RepayOnBehalf<'info> {
+
+    /// Same settlement as `repay_loan`, except the payer supplies the USDC.
+    /// The collateral is returned to the borrower recorded on the loan.
+    pub fn repay_on_behalf(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        repay_in_full(
+            FullRepayment {
+                payer: self.payer.as_ref(),
+                payer_usdc_account: &self.payer_usdc_account,
+                borrower: self.borrower.as_ref(),
+                lender_usdc_account: self.lender_usdc_account.as_ref().map(|account| account.as_ref().as_ref()),
+                loan_request: &mut self.loan_request,
+                collateral_vault: &self.collateral_vault,
+                protocol_vault_usdc: self.protocol_vault_usdc.as_ref().as_ref(),
+                config: &self.config,
+                loan_registry: &mut self.loan_registry,
+                loan_registry_page: &mut self.loan_registry_page,
+                lending_pool: self.lending_pool.as_deref_mut().map(|pool| &mut **pool),
+                collateral_token_vault: self.collateral_token_vault.as_deref(),
+                borrower_collateral_account: self.borrower_collateral_account.as_ref().map(|account| account.as_ref().as_ref()),
+                usdc_mint: self.usdc_mint.key(),
+                token_program: self.token_program.as_ref(),
+            },
+            remaining_accounts,
+        )
+    }
+}
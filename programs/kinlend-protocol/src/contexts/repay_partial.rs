@@ -0,0 +1,215 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::helpers::{
+    calculate_grace_deadline, calculate_repayment_amounts, check_balance, check_deadline_is_not_expired, check_usdc_mint_address,
+    get_token_vault_collateral, get_vault_collateral, is_loan_lender_account, load_tranches, record_pool_loan_earnings, transfer_collateral_tokens, transfer_lamports,
+    transfer_repayment,
+};
+use crate::math::mul_div;
+use crate::state::{CollateralVaultState, ConfigState, LendingPoolState, LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct RepayPartial<'info> {
+
+    //only the borrower can repay his/her loan
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    //borrower's USDC ATA
+    #[account(
+        mut,
+        constraint = borrower_usdc_account.mint == usdc_mint.key(),
+        constraint = borrower_usdc_account.owner == borrower.key() 
+    )]
+    pub borrower_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    //lender's USDC ATA, must belong to the lender recorded in the loan request or the holder of its position NFT,
+    //or be the pool loan's USDC account for a loan funded by the lending pool
+    //a syndicated loan pays its tranche lenders from the remaining accounts instead
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == usdc_mint.key(),
+        constraint = is_loan_lender_account(&loan_request, lender_usdc_account, position_token_account.as_deref().map(|account| &**account)) @ ErrorCode::NotRightLender
+    )]
+    pub lender_usdc_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //token account holding the position NFT of the loan, only for loans funded with fund_loan
+    pub position_token_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //Loan Request account
+    #[account(
+        mut,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //Collateral Vault holding SOL collateral and owning the token vault of SPL collateral
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    //protocol vault USDC
+    #[account(
+        init_if_needed,
+        payer = borrower,
+        token::mint = usdc_mint,
+        token::authority = protocol_vault_authority,
+        seeds = [b"protocol_vault_usdc"],
+        bump
+    )]
+    pub protocol_vault_usdc: Box<Account<'info, TokenAccount>>,
+
+    //authority for protocol_vault_usdc
+    ///CHECK: only used as authority for protocol_vault_usdc
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    //config account which stores mint address of usdc
+    #[account(
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    //lending pool, only for loans funded by the lending pool, counts the lender fee or interest paid to the pool loan
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Option<Box<Account<'info, LendingPoolState>>>,
+
+    //token vault holding SPL collateral, only for loans backed by a whitelisted mint
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account receiving the released SPL collateral
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    //USDC_mint
+    pub usdc_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+
+impl<'info> RepayPartial<'info> {
+
+    /// Repays `amount` of principal. The lender and the protocol are paid their fees on that amount,
+    /// and the same share of the collateral is released to the borrower.
+    /// Repaying the whole remaining balance goes through `repay_loan`, which closes the loan.
+    pub fn repay_partial(&mut self, amount: u64, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+
+        //only a funded loan can be repaid
+        require!(self.loan_request.status == LoanStatus::Funded, ErrorCode::NotFunded);
+
+        //checking deadline, repaying is still possible during the grace window with a late penalty
+        let grace_deadline = calculate_grace_deadline(&self.loan_request)?;
+        check_deadline_is_not_expired(grace_deadline)?;
+
+        let outstanding_principal = self.loan_request.outstanding_principal;
+        require!(amount > 0 && amount < outstanding_principal, ErrorCode::InvalidAmount);
+
+        //checking usdc_mint
+        check_usdc_mint_address(self.config.usdc_mint, self.usdc_mint.key())?;
+
+        //calculating repayment amounts on the repaid principal
+        let (lender_amount, fee, total_amount) = calculate_repayment_amounts(amount, &self.loan_request)?;
+        check_balance(self.borrower_usdc_account.amount, total_amount)?;
+
+        //tranche lenders of a syndicated loan, as (position, lender USDC account) pairs, stay open until the loan is closed
+        let tranches = load_tranches(
+            self.loan_request.key(),
+            self.loan_request.tranche_count,
+            remaining_accounts,
+            Some(self.usdc_mint.key()),
+        )?;
+
+        //principal plus lender fee goes to the lender, or pro rata to the tranche lenders, the protocol fee to the protocol vault
+        transfer_repayment(
+            &self.token_program.to_account_info(),
+            &self.borrower_usdc_account.to_account_info(),
+            &self.borrower.to_account_info(),
+            self.lender_usdc_account.as_ref().map(|account| account.as_ref().as_ref()),
+            &self.protocol_vault_usdc.to_account_info(),
+            &tranches,
+            lender_amount,
+            fee,
+        )?;
+        record_pool_loan_earnings(
+            &self.loan_request,
+            self.lending_pool.as_deref_mut().map(|pool| &mut **pool),
+            lender_amount,
+            amount,
+        )?;
+
+        //release the collateral pro rata, rounded down so the remaining balance stays covered
+        let collateral = self.loan_request.collateral;
+        let released = mul_div(collateral, amount, outstanding_principal)?;
+        self.release_collateral(released)?;
+
+        self.loan_request.outstanding_principal = outstanding_principal
+            .checked_sub(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.loan_request.collateral = collateral
+            .checked_sub(released)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        Ok(())
+    }
+
+
+    //send the released collateral back to the borrower
+    fn release_collateral(&self, released: u64) -> Result<()> {
+        if released == 0 {
+            return Ok(());
+        }
+
+        if self.loan_request.collateral_mint.is_none() {
+            let collateral_info = self.collateral_vault.to_account_info();
+            get_vault_collateral(&collateral_info, self.loan_request.collateral)?;
+            return transfer_lamports(&collateral_info, &self.borrower.to_account_info(), released);
+        }
+
+        let (Some(token_vault), Some(borrower_account)) = (
+            &self.collateral_token_vault,
+            &self.borrower_collateral_account,
+        ) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+
+        get_token_vault_collateral(token_vault.amount, self.loan_request.collateral)?;
+
+        transfer_collateral_tokens(
+            &self.token_program.to_account_info(),
+            &token_vault.to_account_info(),
+            &borrower_account.to_account_info(),
+            &self.collateral_vault.to_account_info(),
+            self.loan_request.key(),
+            self.collateral_vault.bump,
+            released,
+        )
+    }
+}
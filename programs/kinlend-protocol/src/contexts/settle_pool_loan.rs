@@ -0,0 +1,85 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::helpers::settle_pool_loan_usdc;
+use crate::state::{LendingPoolState, LoanRequestState, LoanStatus, PoolLoanState};
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct SettlePoolLoan<'info> {
+    /// Anyone can settle a pool loan, it only moves funds into the pool.
+    pub keeper: Signer<'info>,
+
+    #[account(
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_usdc_vault"],
+        bump
+    )]
+    pub pool_usdc_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_loan", loan_request.key().as_ref()],
+        bump = pool_loan.bump
+    )]
+    pub pool_loan: Box<Account<'info, PoolLoanState>>,
+
+    /// USDC account of the pool loan holding the repayments received so far.
+    #[account(
+        mut,
+        seeds = [b"pool_loan_usdc", loan_request.key().as_ref()],
+        bump
+    )]
+    pub pool_loan_usdc: Box<Account<'info, TokenAccount>>,
+
+    /// Keeper who funded the loan, gets the rent of the pool loan accounts back once the loan is over.
+    /// CHECK: only receives lamports, checked against the pool loan.
+    #[account(
+        mut,
+        address = pool_loan.keeper
+    )]
+    pub rent_receiver: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> SettlePoolLoan<'info> {
+    /// Moves the repayments received by a pool loan into the pool vault and stops counting
+    /// the repaid principal as lent out and the lender fee or interest as unsettled. Once the loan is repaid or refinanced the pool loan accounts are closed.
+    pub fn settle_pool_loan(&mut self) -> Result<()> {
+        // A refinanced loan is no longer the pool's, its payoff is in the pool loan's USDC account.
+        let loan_closed = self.loan_request.status != LoanStatus::Funded
+            || self.loan_request.lender != Some(self.pool_loan.key());
+        let unpaid_principal = if loan_closed { 0 } else { self.loan_request.outstanding_principal };
+
+        let rent_receiver = self.rent_receiver.to_account_info();
+        settle_pool_loan_usdc(
+            &self.token_program.to_account_info(),
+            &self.pool_loan_usdc,
+            &self.pool_usdc_vault.to_account_info(),
+            &mut self.pool_loan,
+            &mut self.lending_pool,
+            unpaid_principal,
+            loan_closed.then_some(&rent_receiver),
+        )?;
+
+        if loan_closed {
+            self.pool_loan.close(rent_receiver)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::MAX_COLLATERAL_HAIRCUT_BPS;
+use crate::state::{CollateralMintState, ConfigState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct UpdateCollateralMint<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    #[account(
+        mut,
+        seeds = [b"collateral_mint", collateral_mint_config.mint.as_ref()],
+        bump = collateral_mint_config.bump
+    )]
+    pub collateral_mint_config: Box<Account<'info, CollateralMintState>>,
+}
+
+impl<'info> UpdateCollateralMint<'info> {
+    /// Changes the feed, haircut or enabled flag of a whitelisted mint.
+    /// Existing loans keep the haircut they were created with.
+    pub fn update_collateral_mint(&mut self, feed_id: [u8; 32], haircut_bps: u16, enabled: bool) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        require!(haircut_bps <= MAX_COLLATERAL_HAIRCUT_BPS, ErrorCode::InvalidConfigParameter);
+
+        self.collateral_mint_config.feed_id = feed_id;
+        self.collateral_mint_config.haircut_bps = haircut_bps;
+        self.collateral_mint_config.enabled = enabled;
+
+        Ok(())
+    }
+}
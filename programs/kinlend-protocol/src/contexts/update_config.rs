@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::Mint;
 
-use crate::state::ConfigState;
+use crate::constants::{
+    BPS_DENOMINATOR, MAX_CLAIM_FEE_BPS, MAX_COLLATERAL_RATIO_BPS, MAX_FORGIVENESS_FEE, MAX_GRACE_PERIOD_DAYS,
+    MAX_LATE_PENALTY_BPS_PER_DAY, MAX_LENDER_FEE_BPS, MAX_LIQUIDATION_FEE_BPS, MAX_PROTOCOL_FEE_BPS,
+};
+use crate::state::{ConfigState, LoanTerms};
 use crate::errors::ErrorCode;
 
 #[derive(Accounts)]
@@ -16,20 +20,74 @@ pub struct UpdateConfig<'info> {
     )]
     pub config: Box<Account<'info, ConfigState>>,
 
-    pub new_usdc_mint: Account<'info, Mint>,
+    //optional, only provided when the usdc mint changes
+    pub new_usdc_mint: Option<Account<'info, Mint>>,
     
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> UpdateConfig<'info> {
-    pub fn update_config(&mut self) -> Result<()> {
+    pub fn update_config(
+        &mut self,
+        new_terms: Option<LoanTerms>,
+        new_treasury: Option<Pubkey>,
+        new_forgiveness_fee: Option<u64>,
+    ) -> Result<()> {
         //checking if the signer is admin
         let signer = self.admin.key();
         self.check_is_admin(signer)?;
 
         //update config
-        let new_usdc_mint_address = self.new_usdc_mint.key();
-        self.config.usdc_mint = new_usdc_mint_address;
+        if let Some(new_usdc_mint) = &self.new_usdc_mint {
+            self.config.usdc_mint = new_usdc_mint.key();
+        }
+
+        //update treasury receiving withdrawn protocol fees
+        if let Some(treasury) = new_treasury {
+            self.config.treasury = treasury;
+        }
+
+        //update the flat fee charged when a lender forgives a loan
+        if let Some(forgiveness_fee) = new_forgiveness_fee {
+            require!(forgiveness_fee <= MAX_FORGIVENESS_FEE, ErrorCode::InvalidConfigParameter);
+            self.config.forgiveness_fee = forgiveness_fee;
+        }
+
+        //update terms for loans created from now on, existing loans keep their snapshot
+        if let Some(terms) = new_terms {
+            self.validate_terms(&terms)?;
+            self.config.terms = terms;
+        }
+
+        Ok(())
+    }
+
+    /// Rejects terms outside of sane bounds:
+    /// - liquidation threshold above 100% and below the initial collateral ratio,
+    /// - collateral ratio capped at MAX_COLLATERAL_RATIO_BPS,
+    /// - every fee capped at its own maximum.
+    fn validate_terms(&self, terms: &LoanTerms) -> Result<()> {
+        require!(
+            terms.liquidation_threshold_bps as u64 > BPS_DENOMINATOR,
+            ErrorCode::InvalidConfigParameter
+        );
+        require!(
+            terms.collateral_ratio_bps > terms.liquidation_threshold_bps
+                && terms.collateral_ratio_bps <= MAX_COLLATERAL_RATIO_BPS,
+            ErrorCode::InvalidConfigParameter
+        );
+        require!(
+            terms.lender_fee_bps <= MAX_LENDER_FEE_BPS
+                && terms.protocol_fee_bps <= MAX_PROTOCOL_FEE_BPS
+                && terms.claim_fee_bps <= MAX_CLAIM_FEE_BPS
+                && terms.liquidation_fee_bps <= MAX_LIQUIDATION_FEE_BPS,
+            ErrorCode::InvalidConfigParameter
+        );
+        require!(
+            terms.grace_period_days <= MAX_GRACE_PERIOD_DAYS
+                && terms.late_penalty_bps_per_day <= MAX_LATE_PENALTY_BPS_PER_DAY,
+            ErrorCode::InvalidConfigParameter
+        );
 
         Ok(())
     }
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::helpers::check_pool_policy;
+use crate::state::{ConfigState, LendingPoolState, PoolPolicy};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct UpdatePoolPolicy<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    #[account(
+        mut,
+        seeds = [b"lending_pool"],
+        bump = lending_pool.bump
+    )]
+    pub lending_pool: Box<Account<'info, LendingPoolState>>,
+}
+
+impl<'info> UpdatePoolPolicy<'info> {
+    //the new policy applies to requests funded from now on, pool loans already running are not affected
+    pub fn update_pool_policy(&mut self, policy: PoolPolicy) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        check_pool_policy(&policy, self.config.terms.collateral_ratio_bps)?;
+        self.lending_pool.policy = policy;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token, TokenAccount};
+
+use crate::constants::MAX_COLLATERAL_HAIRCUT_BPS;
+use crate::state::{CollateralMintState, ConfigState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct WhitelistCollateralMint<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    //SPL mint accepted as collateral
+    pub collateral_mint: Box<Account<'info, Mint>>,
+
+    //per-mint config storing the oracle feed and haircut
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CollateralMintState::INIT_SPACE,
+        seeds = [b"collateral_mint", collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_mint_config: Box<Account<'info, CollateralMintState>>,
+
+    //protocol token account receiving fees taken in this collateral
+    #[account(
+        init,
+        payer = admin,
+        token::mint = collateral_mint,
+        token::authority = protocol_vault_authority,
+        seeds = [b"protocol_vault_token", collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub protocol_collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    ///CHECK: only used as authority for protocol token accounts
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WhitelistCollateralMint<'info> {
+    pub fn whitelist_collateral_mint(
+        &mut self,
+        feed_id: [u8; 32],
+        haircut_bps: u16,
+        bumps: WhitelistCollateralMintBumps,
+    ) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        require!(haircut_bps <= MAX_COLLATERAL_HAIRCUT_BPS, ErrorCode::InvalidConfigParameter);
+
+        self.collateral_mint_config.set_inner(CollateralMintState {
+            mint: self.collateral_mint.key(),
+            feed_id,
+            haircut_bps,
+            decimals: self.collateral_mint.decimals,
+            enabled: true,
+            bump: bumps.collateral_mint_config,
+        });
+
+        Ok(())
+    }
+}
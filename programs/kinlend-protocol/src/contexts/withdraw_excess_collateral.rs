@@ -0,0 +1,151 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+use crate::helpers::{
+    check_deadline_is_not_expired, get_collateral_price, get_token_vault_collateral, get_vault_collateral,
+    transfer_collateral_tokens, transfer_lamports,
+};
+use crate::math::{required_collateral, OraclePrice};
+use crate::state::{CollateralMintState, CollateralVaultState, LoanRequestState, LoanStatus};
+
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct WithdrawExcessCollateral<'info> {
+
+    //only the borrower can withdraw from his/her own loan
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+
+    //Loan Request
+    #[account(
+        mut,
+        seeds = [b"loan_request", borrower.key().as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    //Collateral Vault holding SOL collateral and owning the token vault of SPL collateral
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", loan_request.key().as_ref()],
+        bump = collateral_vault.bump
+    )]
+    pub collateral_vault: Box<Account<'info, CollateralVaultState>>,
+
+    //Pyth pull-oracle price update for the collateral feed
+    pub price_update: Box<Account<'info, PriceUpdateV2>>,
+
+    //whitelisted mint config pricing SPL collateral, only for loans backed by a whitelisted mint
+    pub collateral_mint_config: Option<Box<Account<'info, CollateralMintState>>>,
+
+    //token vault holding SPL collateral
+    #[account(
+        mut,
+        seeds = [b"collateral_token_vault", loan_request.key().as_ref()],
+        bump
+    )]
+    pub collateral_token_vault: Option<Box<Account<'info, TokenAccount>>>,
+
+    //borrower's token account receiving the SPL collateral
+    #[account(
+        mut,
+        constraint = Some(borrower_collateral_account.mint) == loan_request.collateral_mint @ ErrorCode::InvalidCollateralMint,
+        constraint = borrower_collateral_account.owner == borrower.key()
+    )]
+    pub borrower_collateral_account: Option<Box<Account<'info, TokenAccount>>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawExcessCollateral<'info> {
+
+    //withdraw collateral above the initial collateral ratio of the loan
+    pub fn withdraw_excess_collateral(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        self.check_loan_is_open()?;
+
+        //price the collateral at its haircut value, as when the loan was created
+        let (_price, haircut_price, decimals) = get_collateral_price(
+            &self.loan_request,
+            self.collateral_mint_config.as_deref().map(|config| &**config),
+            &self.price_update,
+        )?;
+
+        //what is left after the withdrawal must still cover the initial collateral ratio
+        let remaining = self.loan_request.collateral
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InvalidAmount)?;
+        let required = self.calculate_required_collateral(decimals, &haircut_price)?;
+        require!(remaining >= required, ErrorCode::InsuffientCollateral);
+
+        if self.loan_request.collateral_mint.is_some() {
+            self.transfer_tokens_to_borrower(amount)?;
+        } else {
+            self.transfer_lamports_to_borrower(amount)?;
+        }
+
+        self.loan_request.collateral = remaining;
+
+        Ok(())
+    }
+
+
+    //collateral can only be withdrawn before the loan is settled and before its deadline
+    fn check_loan_is_open(&self) -> Result<()> {
+        match self.loan_request.status {
+            LoanStatus::Requested => Ok(()),
+            LoanStatus::Funded => {
+                let repayment_time = self.loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+                check_deadline_is_not_expired(repayment_time)
+            }
+            _ => Err(ErrorCode::LoanAlreadyClosed.into()),
+        }
+    }
+
+
+    /// Same rule as `create_loan_request`, with the collateral ratio snapshotted on the loan
+    /// and the principal still outstanding after partial repayments:
+    /// required_collateral = ceil(outstanding_principal * collateral_ratio / price)
+    fn calculate_required_collateral(&self, decimals: u32, price: &OraclePrice) -> Result<u64> {
+        let collateral_ratio_bps = self.loan_request.terms.collateral_ratio_bps as u64;
+        required_collateral(self.loan_request.outstanding_principal, collateral_ratio_bps, decimals, price)
+    }
+
+
+    //move lamports out of the collateral vault, never touching its rent deposit
+    fn transfer_lamports_to_borrower(&self, amount: u64) -> Result<()> {
+        let collateral_info = self.collateral_vault.to_account_info();
+        get_vault_collateral(&collateral_info, self.loan_request.collateral)?;
+        transfer_lamports(&collateral_info, &self.borrower.to_account_info(), amount)
+    }
+
+
+    //move tokens out of the collateral token vault
+    fn transfer_tokens_to_borrower(&self, amount: u64) -> Result<()> {
+        let (Some(token_vault), Some(borrower_account), Some(token_program)) = (
+            &self.collateral_token_vault,
+            &self.borrower_collateral_account,
+            &self.token_program,
+        ) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+
+        get_token_vault_collateral(token_vault.amount, self.loan_request.collateral)?;
+
+        transfer_collateral_tokens(
+            &token_program.to_account_info(),
+            &token_vault.to_account_info(),
+            &borrower_account.to_account_info(),
+            &self.collateral_vault.to_account_info(),
+            self.loan_request.key(),
+            self.collateral_vault.bump,
+            amount,
+        )
+    }
+}
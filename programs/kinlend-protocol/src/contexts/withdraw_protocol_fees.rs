@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::helpers::transfer_lamports;
+use crate::state::{ConfigState, ProtocolVaultState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFees<'info> {
+    /// Admin withdrawing the fees. Must be the authority stored in config.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Config account storing the authority and the treasury.
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    /// Protocol Vault account (PDA) holding SOL fees from claims and liquidations.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault"],
+        bump = protocol_vault.bump
+    )]
+    pub protocol_vault: Box<Account<'info, ProtocolVaultState>>,
+
+    /// Treasury receiving the fees, must match the one stored in config.
+    #[account(
+        mut,
+        address = config.treasury @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> WithdrawProtocolFees<'info> {
+    pub fn withdraw_protocol_fees(&mut self, amount: u64) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        //only lamports above the rent-exempt minimum can be withdrawn
+        let available = self.get_withdrawable_amount()?;
+        require!(amount > 0 && amount <= available, ErrorCode::InvalidAmount);
+
+        self.transfer_to_treasury(amount)?;
+
+        Ok(())
+    }
+
+    /// Lamports held by the protocol vault above its rent-exempt minimum.
+    fn get_withdrawable_amount(&self) -> Result<u64> {
+        let vault_info = self.protocol_vault.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(vault_info.data_len());
+        Ok(vault_info.lamports().saturating_sub(rent_exempt))
+    }
+
+    /// The protocol vault is owned by this program, so lamports are moved directly.
+    fn transfer_to_treasury(&mut self, amount: u64) -> Result<()> {
+        transfer_lamports(
+            &self.protocol_vault.to_account_info(),
+            &self.treasury.to_account_info(),
+            amount,
+        )
+    }
+}
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
+
+use crate::state::{CollateralMintState, ConfigState};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFeesToken<'info> {
+    /// Admin withdrawing the fees. Must be the authority stored in config.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Config account storing the authority and the treasury.
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    /// Whitelisted collateral mint the fees are held in.
+    #[account(
+        seeds = [b"collateral_mint", collateral_mint_config.mint.as_ref()],
+        bump = collateral_mint_config.bump
+    )]
+    pub collateral_mint_config: Box<Account<'info, CollateralMintState>>,
+
+    /// Protocol token account holding collateral fees from claims and liquidations.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault_token", collateral_mint_config.mint.as_ref()],
+        bump
+    )]
+    pub protocol_collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    ///CHECK: only used as authority for protocol token accounts
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    /// Treasury token account, must be owned by the treasury stored in config.
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == collateral_mint_config.mint @ ErrorCode::InvalidCollateralMint,
+        constraint = treasury_token_account.owner == config.treasury @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> WithdrawProtocolFeesToken<'info> {
+    pub fn withdraw_protocol_fees_token(&mut self, amount: u64, authority_bump: u8) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        require!(
+            amount > 0 && amount <= self.protocol_collateral_vault.amount,
+            ErrorCode::InvalidAmount
+        );
+
+        let authority_seeds: &[&[u8]] = &[b"protocol_vault_usdc_authority", &[authority_bump]];
+        let signer_seeds = &[authority_seeds];
+
+        let cpi_accounts = Transfer {
+            from: self.protocol_collateral_vault.to_account_info(),
+            to: self.treasury_token_account.to_account_info(),
+            authority: self.protocol_vault_authority.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer(cpi_ctx, amount)
+    }
+}
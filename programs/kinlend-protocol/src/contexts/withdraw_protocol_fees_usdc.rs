@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer, Token, TokenAccount, Transfer};
+
+use crate::state::ConfigState;
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+pub struct WithdrawProtocolFeesUsdc<'info> {
+    /// Admin withdrawing the fees. Must be the authority stored in config.
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Config account storing the authority, the usdc mint and the treasury.
+    #[account(
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Box<Account<'info, ConfigState>>,
+
+    /// Protocol Vault USDC account holding repayment fees.
+    #[account(
+        mut,
+        seeds = [b"protocol_vault_usdc"],
+        bump
+    )]
+    pub protocol_vault_usdc: Box<Account<'info, TokenAccount>>,
+
+    ///CHECK: only used as authority for protocol_vault_usdc
+    #[account(
+        seeds = [b"protocol_vault_usdc_authority"],
+        bump
+    )]
+    pub protocol_vault_authority: AccountInfo<'info>,
+
+    /// Treasury USDC account, must be owned by the treasury stored in config.
+    #[account(
+        mut,
+        constraint = treasury_usdc_account.mint == config.usdc_mint @ ErrorCode::IncorrectUsdcMintAddress,
+        constraint = treasury_usdc_account.owner == config.treasury @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> WithdrawProtocolFeesUsdc<'info> {
+    pub fn withdraw_protocol_fees_usdc(&mut self, amount: u64, authority_bump: u8) -> Result<()> {
+        //checking if the signer is admin
+        require!(self.admin.key() == self.config.authority, ErrorCode::NotAdmin);
+
+        //token accounts are rent-exempt on their own, the whole token balance can be withdrawn
+        require!(
+            amount > 0 && amount <= self.protocol_vault_usdc.amount,
+            ErrorCode::InvalidAmount
+        );
+
+        self.transfer_to_treasury(amount, authority_bump)?;
+
+        Ok(())
+    }
+
+    /// Transfers USDC from the protocol vault to the treasury, signed by the vault authority PDA.
+    fn transfer_to_treasury(&self, amount: u64, authority_bump: u8) -> Result<()> {
+        let authority_seeds: &[&[u8]] = &[b"protocol_vault_usdc_authority", &[authority_bump]];
+        let signer_seeds = &[authority_seeds];
+
+        let cpi_accounts = Transfer {
+            from: self.protocol_vault_usdc.to_account_info(),
+            to: self.treasury_usdc_account.to_account_info(),
+            authority: self.protocol_vault_authority.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer(cpi_ctx, amount)
+    }
+}
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::helpers::{close_tranche_escrow, transfer_from_tranche_escrow};
+use crate::state::{LenderPositionState, LoanRequestState, LoanStatus};
+use crate::errors::ErrorCode;
+
+#[derive(Accounts)]
+#[instruction(loan_id: u64)]
+pub struct WithdrawTranche<'info> {
+    /// Lender taking back their tranche.
+    #[account(mut)]
+    pub lender: Signer<'info>,
+
+    /// Loan Request account, owner of the tranche escrow.
+    #[account(
+        mut,
+        seeds = [b"loan_request", loan_request.borrower.as_ref(), &loan_id.to_le_bytes()],
+        bump
+    )]
+    pub loan_request: Box<Account<'info, LoanRequestState>>,
+
+    /// Position of the lender, closed and its rent returned to the lender.
+    #[account(
+        mut,
+        close = lender,
+        seeds = [b"lender_position", loan_request.key().as_ref(), lender.key().as_ref()],
+        bump = lender_position.bump,
+        constraint = !lender_position.settled @ ErrorCode::InvalidTrancheAccounts
+    )]
+    pub lender_position: Box<Account<'info, LenderPositionState>>,
+
+    /// Escrow holding the tranches of the loan.
+    #[account(
+        mut,
+        seeds = [b"tranche_escrow", loan_request.key().as_ref()],
+        bump
+    )]
+    pub tranche_escrow: Box<Account<'info, TokenAccount>>,
+
+    /// Lender's USDC token account receiving the refund.
+    #[account(
+        mut,
+        constraint = lender_usdc_account.mint == tranche_escrow.mint,
+        constraint = lender_usdc_account.owner == lender.key()
+    )]
+    pub lender_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> WithdrawTranche<'info> {
+    /// Refunds the lender's tranche from the escrow while the loan is not fully subscribed yet.
+    /// The last lender to leave also gets whatever is left in the escrow and its rent.
+    pub fn withdraw_tranche(&mut self, loan_request_bump: u8) -> Result<()> {
+        // Once funded the escrow has been released to the borrower.
+        require!(self.loan_request.status == LoanStatus::Requested, ErrorCode::AlreadyFunded);
+
+        let amount = self.lender_position.amount;
+        self.loan_request.subscribed_amount = self.loan_request.subscribed_amount
+            .checked_sub(amount)
+            .ok_or(ErrorCode::CalculationError)?;
+        self.loan_request.tranche_count = self.loan_request.tranche_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        let last_tranche = self.loan_request.tranche_count == 0;
+        let refund = if last_tranche { self.tranche_escrow.amount } else { amount };
+
+        let token_program = self.token_program.to_account_info();
+        let tranche_escrow = self.tranche_escrow.to_account_info();
+        let loan_request = self.loan_request.to_account_info();
+        let borrower = self.loan_request.borrower;
+        let loan_id = self.loan_request.loan_id;
+
+        transfer_from_tranche_escrow(
+            &token_program,
+            &tranche_escrow,
+            &self.lender_usdc_account.to_account_info(),
+            &loan_request,
+            borrower,
+            loan_id,
+            loan_request_bump,
+            refund,
+        )?;
+
+        if last_tranche {
+            close_tranche_escrow(
+                &token_program,
+                &tranche_escrow,
+                &self.lender.to_account_info(),
+                &loan_request,
+                borrower,
+                loan_id,
+                loan_request_bump,
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -63,4 +63,79 @@ pub enum ErrorCode {
     #[msg("NotAdmin")]
     NotAdmin,
 
+    #[msg("Oracle price is stale")]
+    StaleOracle,
+
+    #[msg("Invalid config parameter")]
+    InvalidConfigParameter,
+
+    #[msg("Invalid amount")]
+    InvalidAmount,
+
+    #[msg("Treasury does not match config")]
+    InvalidTreasury,
+
+    #[msg("Loan is already closed")]
+    LoanAlreadyClosed,
+
+    #[msg("Collateral mint is not whitelisted")]
+    InvalidCollateralMint,
+
+    #[msg("Collateral token account is missing")]
+    MissingCollateralAccount,
+
+    #[msg("Invalid interest rate")]
+    InvalidInterestRate,
+
+    #[msg("Invalid loan extension")]
+    InvalidExtension,
+
+    #[msg("No loan extension is pending")]
+    NoPendingExtension,
+
+    #[msg("Fee token account is missing")]
+    MissingFeeAccount,
+
+    #[msg("Too many allowed lenders")]
+    TooManyAllowedLenders,
+
+    #[msg("Loan is partially funded by tranches")]
+    LoanPartiallySubscribed,
+
+    #[msg("Too many lender tranches")]
+    TooManyTranches,
+
+    #[msg("Lender position accounts are missing or invalid")]
+    InvalidTrancheAccounts,
+
+    #[msg("Lender position is not settled yet")]
+    PositionNotSettled,
+
+    #[msg("Loan does not match the offer terms")]
+    InvalidLoanOffer,
+
+    #[msg("Invalid lending pool policy")]
+    InvalidPoolPolicy,
+
+    #[msg("Loan request does not meet the pool policy")]
+    LoanOutsidePoolPolicy,
+
+    #[msg("Pool exposure cap exceeded")]
+    PoolExposureExceeded,
+
+    #[msg("Not enough USDC available in the pool")]
+    InsufficientPoolLiquidity,
+
+    #[msg("Syndicated loans can't be extended or forgiven")]
+    SyndicatedLoanNotSupported,
+
+    #[msg("Loan duration must be at least one day")]
+    InvalidDuration,
+
+    #[msg("Amount too small to mint or redeem pool shares")]
+    PoolAmountTooSmall,
+
+    #[msg("Lending pool account is required for a pool loan")]
+    MissingLendingPool,
+
 }
\ No newline at end of file
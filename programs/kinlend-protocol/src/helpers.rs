@@ -1,6 +1,24 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use anchor_spl::token::{
+    close_account, mint_to, set_authority, transfer, CloseAccount, MintTo, SetAuthority, Token, TokenAccount, Transfer,
+};
+use pyth_solana_receiver_sdk::error::GetPriceError;
+use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, FeedId, PriceUpdateV2};
 
+use crate::constants::{
+    BPS_DENOMINATOR, LOAN_REGISTRY_PAGE_SIZE, OFFER_REGISTRY_PAGE_SIZE, MAX_AGE, MAX_ALLOWED_LENDERS, MAX_APR_BPS,
+    MAX_COLLATERAL_RATIO_BPS, SOL_DECIMALS, SOL_USD_FEED_ID,
+};
 use crate::errors::ErrorCode;
+use crate::math::{
+    accrued_interest, apply_bps, apply_haircut, collateral_value_usd, grace_deadline, late_penalty, required_collateral,
+    split_pro_rata, vault_collateral, OraclePrice,
+};
+use crate::state::{
+    CollateralMintState, CollateralVaultState, ConfigState, LenderPositionState, LendingPoolState, LoanRegistryPageState, LoanRegistryState, LoanRequestState,
+    LoanStatus, OfferRegistryPageState, OfferRegistryState, PoolLoanState, PoolPolicy,
+};
 
 
 pub fn check_deadline_is_expired(repayment_time: i64) -> Result<()> {
@@ -74,4 +92,1059 @@ pub fn check_balance(balance: u64, total_amount:u64) -> Result<()> {
             return Err(ErrorCode::InsufficientBalance.into());
         }
     Ok(())
-}
\ No newline at end of file
+}
+
+
+/// Checks the allowlist of a private loan request, an empty list keeps the request open to anyone.
+pub fn check_allowed_lenders(allowed_lenders: &[Pubkey]) -> Result<()> {
+    require!(allowed_lenders.len() <= MAX_ALLOWED_LENDERS, ErrorCode::TooManyAllowedLenders);
+
+    Ok(())
+}
+
+
+/// Checks that a lender may fund a loan request, only allowed lenders can fund a private one.
+pub fn check_allowed_lender(allowed_lenders: &[Pubkey], lender: Pubkey) -> Result<()> {
+    require!(
+        allowed_lenders.is_empty() || allowed_lenders.contains(&lender),
+        ErrorCode::UnauthorizedLender
+    );
+
+    Ok(())
+}
+
+
+/// Reads SOL/USD from a Pyth pull-oracle `PriceUpdateV2` account.
+/// The update must match `SOL_USD_FEED_ID` and be no older than `MAX_AGE` seconds,
+/// otherwise `StaleOracle` or `InvalidOracleAccount` is returned.
+pub fn get_sol_price(price_update: &PriceUpdateV2) -> Result<OraclePrice> {
+    let feed_id = get_feed_id_from_hex(SOL_USD_FEED_ID)?;
+    get_oracle_price(price_update, &feed_id)
+}
+
+
+/// Reads the price of `feed_id` from a Pyth pull-oracle `PriceUpdateV2` account,
+/// no older than `MAX_AGE` seconds.
+pub fn get_oracle_price(price_update: &PriceUpdateV2, feed_id: &FeedId) -> Result<OraclePrice> {
+    let clock = Clock::get()?;
+    let price = price_update
+        .get_price_no_older_than(&clock, MAX_AGE, feed_id)
+        .map_err(|err| match err {
+            GetPriceError::PriceTooOld => ErrorCode::StaleOracle,
+            _ => ErrorCode::InvalidOracleAccount,
+        })?;
+
+    require!(price.price > 0, ErrorCode::InvalidOracleAccount);
+
+    Ok(OraclePrice {
+        price: price.price as u64,
+        exponent: price.exponent,
+    })
+}
+
+
+/// Prices the collateral of a loan: native SOL through the SOL/USD feed,
+/// SPL collateral through the feed stored in its whitelisted mint config.
+/// Returns the market price, the price after the loan's haircut and the collateral decimals.
+pub fn get_collateral_price(
+    loan_request: &LoanRequestState,
+    collateral_mint_config: Option<&CollateralMintState>,
+    price_update: &PriceUpdateV2,
+) -> Result<(OraclePrice, OraclePrice, u32)> {
+    let (price, decimals) = match loan_request.collateral_mint {
+        Some(collateral_mint) => {
+            let mint_config = collateral_mint_config.ok_or(ErrorCode::MissingCollateralAccount)?;
+            require!(mint_config.mint == collateral_mint, ErrorCode::InvalidCollateralMint);
+            (get_oracle_price(price_update, &mint_config.feed_id)?, mint_config.decimals as u32)
+        }
+        None => (get_sol_price(price_update)?, SOL_DECIMALS),
+    };
+    let haircut_price = apply_haircut(&price, loan_request.collateral_haircut_bps)?;
+
+    Ok((price, haircut_price, decimals))
+}
+
+
+/// Collateral (base units) worth `lender_due` (USDC) at the oracle price.
+pub fn calculate_lender_claim(lender_due: u64, decimals: u32, price: &OraclePrice) -> Result<u64> {
+    required_collateral(lender_due, BPS_DENOMINATOR, decimals, price)
+}
+
+
+/// Checks an optional APR chosen by the borrower, `None` keeps the flat lender fee.
+pub fn check_apr_bps(apr_bps: Option<u16>) -> Result<()> {
+    if let Some(apr_bps) = apr_bps {
+        require!(apr_bps > 0 && apr_bps <= MAX_APR_BPS, ErrorCode::InvalidInterestRate);
+    }
+
+    Ok(())
+}
+
+
+/// Seconds a funded loan has been running: from funding (`repayment_time - duration`)
+/// to now, capped at the deadline.
+pub fn calculate_elapsed_seconds(loan_request: &LoanRequestState) -> Result<u64> {
+    let repayment_time = loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+    let duration_seconds = loan_request.duration_days
+                                .checked_mul(86400)
+                                .ok_or(ErrorCode::Overflow)? as i64;
+    let funded_at = repayment_time
+                        .checked_sub(duration_seconds)
+                        .ok_or(ErrorCode::Overflow)?;
+
+    let now = Clock::get()?.unix_timestamp.min(repayment_time);
+
+    Ok(now.saturating_sub(funded_at).max(0) as u64)
+}
+
+
+/// What the lender is owed on `principal`: the principal plus the flat lender fee,
+/// or plus the interest accrued so far for loans with an APR.
+pub fn calculate_lender_due(principal: u64, loan_request: &LoanRequestState) -> Result<u64> {
+    let premium = match loan_request.apr_bps {
+        Some(apr_bps) => accrued_interest(principal, apr_bps, calculate_elapsed_seconds(loan_request)?)?,
+        None => apply_bps(principal, loan_request.terms.lender_fee_bps as u64)?,
+    };
+
+    principal.checked_add(premium).ok_or(ErrorCode::CalculationError.into())
+}
+
+
+/// Everything the lender is still owed when the loan is settled in full:
+/// the lender due on the outstanding principal plus accepted extension fees.
+pub fn calculate_outstanding_lender_due(loan_request: &LoanRequestState) -> Result<u64> {
+    calculate_lender_due(loan_request.outstanding_principal, loan_request)?
+        .checked_add(loan_request.extension_fees)
+        .ok_or(ErrorCode::CalculationError.into())
+}
+
+
+/// End of the grace window: the deadline plus the loan's grace period.
+/// The borrower can still repay until then, and collateral can only be claimed after it.
+pub fn calculate_grace_deadline(loan_request: &LoanRequestState) -> Result<i64> {
+    let repayment_time = loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+
+    grace_deadline(repayment_time, loan_request.terms.grace_period_days)
+}
+
+
+/// Late penalty on `principal` for repaying after the deadline, charged per started day late.
+pub fn calculate_late_penalty(principal: u64, loan_request: &LoanRequestState) -> Result<u64> {
+    let repayment_time = loan_request.repayment_time.ok_or(ErrorCode::NotFunded)?;
+    let now = Clock::get()?.unix_timestamp;
+
+    late_penalty(principal, repayment_time, now, loan_request.terms.late_penalty_bps_per_day)
+}
+
+
+/// Repayment amounts for `principal` under the loan's terms:
+/// - lender_amount: principal plus lender fee (104% by default) or accrued interest,
+///   plus the late penalty when repaid during the grace window (USDC).
+/// - fee: protocol fee on the principal (1% by default) (USDC).
+/// - total_amount: Sum of the two (used for balance checks).
+pub fn calculate_repayment_amounts(principal: u64, loan_request: &LoanRequestState) -> Result<(u64, u64, u64)> {
+    let lender_amount = calculate_lender_due(principal, loan_request)?
+        .checked_add(calculate_late_penalty(principal, loan_request)?)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let fee = apply_bps(principal, loan_request.terms.protocol_fee_bps as u64)?;
+
+    let total_amount = lender_amount.checked_add(fee).ok_or(ErrorCode::CalculationError)?;
+
+    Ok((lender_amount, fee, total_amount))
+}
+
+
+/// Repayment amounts to settle a loan in full. Partial repayments have already paid their share,
+/// so only the outstanding principal is due, plus the fees of any accepted extension which go to the lender.
+pub fn calculate_full_repayment_amounts(loan_request: &LoanRequestState) -> Result<(u64, u64, u64)> {
+    let (lender_amount, fee, total_amount) =
+        calculate_repayment_amounts(loan_request.outstanding_principal, loan_request)?;
+    let extension_fees = loan_request.extension_fees;
+
+    Ok((
+        lender_amount.checked_add(extension_fees).ok_or(ErrorCode::CalculationError)?,
+        fee,
+        total_amount.checked_add(extension_fees).ok_or(ErrorCode::CalculationError)?,
+    ))
+}
+
+
+/// Moves a loan to `next` if the transition is legal, otherwise returns a typed error:
+/// - `AlreadyFunded` when a funded loan is funded or cancelled again,
+/// - `NotFunded` when a requested loan is repaid, claimed, liquidated or forgiven,
+/// - `LoanAlreadyClosed` for any transition out of a terminal state.
+pub fn transition_loan_status(status: &mut LoanStatus, next: LoanStatus) -> Result<()> {
+    let allowed = matches!(
+        (*status, next),
+        (LoanStatus::Requested, LoanStatus::Funded)
+            | (LoanStatus::Requested, LoanStatus::Cancelled)
+            | (LoanStatus::Funded, LoanStatus::Repaid)
+            | (LoanStatus::Funded, LoanStatus::Defaulted)
+            | (LoanStatus::Funded, LoanStatus::Liquidated)
+            | (LoanStatus::Funded, LoanStatus::Forgiven)
+    );
+
+    if !allowed {
+        return Err(match *status {
+            LoanStatus::Requested => ErrorCode::NotFunded,
+            LoanStatus::Funded => ErrorCode::AlreadyFunded,
+            _ => ErrorCode::LoanAlreadyClosed,
+        }
+        .into());
+    }
+
+    *status = next;
+    Ok(())
+}
+
+
+/// Starts a loan funded by `lender`: marks it funded and sets the repayment deadline counted from now.
+pub fn start_loan(loan_request: &mut LoanRequestState, lender: Pubkey) -> Result<()> {
+    transition_loan_status(&mut loan_request.status, LoanStatus::Funded)?;
+    loan_request.lender = Some(lender);
+    loan_request.repayment_time = Some(calculate_repayment_time(loan_request.duration_days)?);
+
+    Ok(())
+}
+
+
+/// Transfers USDC put up by a lender, from the lender's token account or from an escrow they funded.
+/// `signer_seeds` is empty when the lender signs, and holds the seeds of the escrow owner otherwise.
+pub fn transfer_loan_funds<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: from.clone(),
+        to: destination.clone(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    transfer(cpi_ctx, amount)
+}
+
+
+/// Moves lamports out of an account owned by this program (collateral or protocol vault).
+/// Those accounts carry Anchor data, so the System Program cannot debit them and lamports are moved directly.
+pub fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    **from.try_borrow_mut_lamports()? = from.lamports().checked_sub(amount).ok_or(ErrorCode::CalculationError)?;
+    **to.try_borrow_mut_lamports()? = to.lamports().checked_add(amount).ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Returns the collateral recorded on the loan after checking the vault still holds it
+/// on top of its rent-exempt deposit. The rent deposit is never counted as collateral.
+pub fn get_vault_collateral(collateral_vault: &AccountInfo, collateral: u64) -> Result<u64> {
+    let rent_exempt = Rent::get()?.minimum_balance(collateral_vault.data_len());
+
+    vault_collateral(collateral_vault.lamports(), rent_exempt, collateral)
+}
+
+
+/// Adds a loan request to a registry page and updates the root counters.
+pub fn add_to_loan_registry(
+    loan_registry: &mut LoanRegistryState,
+    loan_registry_page: &mut LoanRegistryPageState,
+    loan_request_key: Pubkey,
+) -> Result<()> {
+    require!(
+        loan_registry_page.loan_requests.len() < LOAN_REGISTRY_PAGE_SIZE,
+        ErrorCode::PageIsFull
+    );
+
+    loan_registry_page.loan_requests.push(loan_request_key);
+
+    loan_registry.total_loans = loan_registry.total_loans
+        .checked_add(1)
+        .ok_or(ErrorCode::CalculationError)?;
+    loan_registry.free_slots = loan_registry.free_slots
+        .checked_sub(1)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Removes a loan request from its registry page and updates the root counters.
+pub fn remove_from_loan_registry(
+    loan_registry: &mut LoanRegistryState,
+    loan_registry_page: &mut LoanRegistryPageState,
+    loan_request_key: Pubkey,
+) -> Result<()> {
+    // Find the index of the loan request in the page
+    let position = loan_registry_page.loan_requests.iter()
+        .position(|&pubkey| pubkey == loan_request_key)
+        .ok_or(ErrorCode::NotFoundInRegistry)?;
+
+    // Remove the loan request from the page
+    loan_registry_page.loan_requests.swap_remove(position);
+
+    // Decrement the total loans counter and free the slot
+    loan_registry.total_loans = loan_registry.total_loans
+        .checked_sub(1)
+        .ok_or(ErrorCode::CalculationError)?;
+    loan_registry.free_slots = loan_registry.free_slots
+        .checked_add(1)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Adds a loan offer to an offer registry page and updates the root counters.
+pub fn add_to_offer_registry(
+    offer_registry: &mut OfferRegistryState,
+    offer_registry_page: &mut OfferRegistryPageState,
+    loan_offer_key: Pubkey,
+) -> Result<()> {
+    require!(
+        offer_registry_page.loan_offers.len() < OFFER_REGISTRY_PAGE_SIZE,
+        ErrorCode::PageIsFull
+    );
+
+    offer_registry_page.loan_offers.push(loan_offer_key);
+
+    offer_registry.total_offers = offer_registry.total_offers
+        .checked_add(1)
+        .ok_or(ErrorCode::CalculationError)?;
+    offer_registry.free_slots = offer_registry.free_slots
+        .checked_sub(1)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Removes a loan offer from its offer registry page and updates the root counters.
+pub fn remove_from_offer_registry(
+    offer_registry: &mut OfferRegistryState,
+    offer_registry_page: &mut OfferRegistryPageState,
+    loan_offer_key: Pubkey,
+) -> Result<()> {
+    let position = offer_registry_page.loan_offers.iter()
+        .position(|&pubkey| pubkey == loan_offer_key)
+        .ok_or(ErrorCode::NotFoundInRegistry)?;
+
+    offer_registry_page.loan_offers.swap_remove(position);
+
+    offer_registry.total_offers = offer_registry.total_offers
+        .checked_sub(1)
+        .ok_or(ErrorCode::CalculationError)?;
+    offer_registry.free_slots = offer_registry.free_slots
+        .checked_add(1)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Transfers SPL collateral out of a loan's collateral token vault.
+/// The token vault is owned by the collateral vault PDA, which signs with its seeds.
+pub fn transfer_collateral_tokens<'info>(
+    token_program: &AccountInfo<'info>,
+    collateral_token_vault: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    collateral_vault: &AccountInfo<'info>,
+    loan_request_key: Pubkey,
+    collateral_vault_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let collateral_seeds: &[&[u8]] = &[
+        b"collateral_vault",
+        loan_request_key.as_ref(),
+        &[collateral_vault_bump],
+    ];
+    let signer_seeds = &[collateral_seeds];
+
+    let cpi_accounts = Transfer {
+        from: collateral_token_vault.clone(),
+        to: destination.clone(),
+        authority: collateral_vault.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    transfer(cpi_ctx, amount)
+}
+
+
+/// Returns the token collateral recorded on the loan after checking the token vault still holds it.
+pub fn get_token_vault_collateral(vault_amount: u64, collateral: u64) -> Result<u64> {
+    require!(collateral > 0 && vault_amount >= collateral, ErrorCode::NoCollateral);
+
+    Ok(collateral)
+}
+
+
+/// Sends what is left in a collateral token vault to the borrower's token account,
+/// then closes the empty vault and returns its rent to `rent_destination`.
+#[allow(clippy::too_many_arguments)]
+pub fn release_token_collateral<'info>(
+    token_program: &AccountInfo<'info>,
+    collateral_token_vault: &AccountInfo<'info>,
+    borrower_collateral_account: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    collateral_vault: &AccountInfo<'info>,
+    loan_request_key: Pubkey,
+    collateral_vault_bump: u8,
+    remaining: u64,
+) -> Result<()> {
+    transfer_collateral_tokens(
+        token_program,
+        collateral_token_vault,
+        borrower_collateral_account,
+        collateral_vault,
+        loan_request_key,
+        collateral_vault_bump,
+        remaining,
+    )?;
+
+    let collateral_seeds: &[&[u8]] = &[
+        b"collateral_vault",
+        loan_request_key.as_ref(),
+        &[collateral_vault_bump],
+    ];
+    let signer_seeds = &[collateral_seeds];
+
+    let cpi_accounts = CloseAccount {
+        account: collateral_token_vault.clone(),
+        destination: rent_destination.clone(),
+        authority: collateral_vault.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    close_account(cpi_ctx)
+}
+
+
+
+/// Transfers USDC out of the tranche escrow of a syndicated loan.
+/// The escrow is owned by the loan request PDA, which signs with its seeds.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_from_tranche_escrow<'info>(
+    token_program: &AccountInfo<'info>,
+    tranche_escrow: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+    loan_request: &AccountInfo<'info>,
+    borrower: Pubkey,
+    loan_id: u64,
+    loan_request_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let loan_id_bytes = loan_id.to_le_bytes();
+    let loan_request_seeds: &[&[u8]] = &[
+        b"loan_request",
+        borrower.as_ref(),
+        &loan_id_bytes,
+        &[loan_request_bump],
+    ];
+    let signer_seeds = &[loan_request_seeds];
+
+    let cpi_accounts = Transfer {
+        from: tranche_escrow.clone(),
+        to: destination.clone(),
+        authority: loan_request.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    transfer(cpi_ctx, amount)
+}
+
+
+/// Closes the empty tranche escrow of a syndicated loan and returns its rent to `rent_destination`.
+pub fn close_tranche_escrow<'info>(
+    token_program: &AccountInfo<'info>,
+    tranche_escrow: &AccountInfo<'info>,
+    rent_destination: &AccountInfo<'info>,
+    loan_request: &AccountInfo<'info>,
+    borrower: Pubkey,
+    loan_id: u64,
+    loan_request_bump: u8,
+) -> Result<()> {
+    let loan_id_bytes = loan_id.to_le_bytes();
+    let loan_request_seeds: &[&[u8]] = &[
+        b"loan_request",
+        borrower.as_ref(),
+        &loan_id_bytes,
+        &[loan_request_bump],
+    ];
+    let signer_seeds = &[loan_request_seeds];
+
+    let cpi_accounts = CloseAccount {
+        account: tranche_escrow.clone(),
+        destination: rent_destination.clone(),
+        authority: loan_request.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+
+    close_account(cpi_ctx)
+}
+
+
+/// Lender position of a syndicated loan, passed to a settlement with the account receiving its share.
+pub struct Tranche<'info> {
+    pub position: LenderPositionState,
+    pub position_info: AccountInfo<'info>,
+    pub destination: AccountInfo<'info>,
+}
+
+
+/// Loads the lender positions of a syndicated loan from the remaining accounts of a settlement.
+/// They come in (lender position, destination) pairs, one pair for every open tranche.
+/// The destination is the lender's token account of `mint`, or the lender's wallet for SOL.
+pub fn load_tranches<'info>(
+    loan_request_key: Pubkey,
+    tranche_count: u16,
+    remaining_accounts: &[AccountInfo<'info>],
+    mint: Option<Pubkey>,
+) -> Result<Vec<Tranche<'info>>> {
+    require!(
+        remaining_accounts.len() == tranche_count as usize * 2,
+        ErrorCode::InvalidTrancheAccounts
+    );
+
+    let mut tranches: Vec<Tranche<'info>> = Vec::with_capacity(tranche_count as usize);
+    for pair in remaining_accounts.chunks(2) {
+        let (position_info, destination) = (&pair[0], &pair[1]);
+
+        require!(position_info.owner == &crate::ID, ErrorCode::InvalidTrancheAccounts);
+        let position = LenderPositionState::try_deserialize(&mut &position_info.try_borrow_data()?[..])?;
+        require!(
+            position.loan_request == loan_request_key
+                && !position.settled
+                && tranches.iter().all(|tranche| tranche.position_info.key() != position_info.key()),
+            ErrorCode::InvalidTrancheAccounts
+        );
+
+        match mint {
+            Some(mint) => {
+                require!(destination.owner == &Token::id(), ErrorCode::InvalidTrancheAccounts);
+                let token_account = TokenAccount::try_deserialize(&mut &destination.try_borrow_data()?[..])?;
+                require!(
+                    token_account.mint == mint && token_account.owner == position.lender,
+                    ErrorCode::InvalidTrancheAccounts
+                );
+            }
+            None => require_keys_eq!(destination.key(), position.lender, ErrorCode::InvalidTrancheAccounts),
+        }
+
+        tranches.push(Tranche {
+            position,
+            position_info: position_info.clone(),
+            destination: destination.clone(),
+        });
+    }
+
+    Ok(tranches)
+}
+
+
+/// Splits `amount` across the tranches of a syndicated loan pro rata to what each lender put in.
+pub fn calculate_tranche_shares(tranches: &[Tranche], amount: u64) -> Result<Vec<u64>> {
+    let weights: Vec<u64> = tranches.iter().map(|tranche| tranche.position.amount).collect();
+    split_pro_rata(amount, &weights)
+}
+
+
+/// Pays `amount` of USDC from `from` to the lenders of a syndicated loan, pro rata to their tranches.
+pub fn transfer_usdc_to_tranches<'info>(
+    token_program: &AccountInfo<'info>,
+    from: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    tranches: &[Tranche<'info>],
+    amount: u64,
+) -> Result<()> {
+    let shares = calculate_tranche_shares(tranches, amount)?;
+    for (tranche, share) in tranches.iter().zip(shares) {
+        if share == 0 {
+            continue;
+        }
+
+        let cpi_accounts = Transfer {
+            from: from.clone(),
+            to: tranche.destination.clone(),
+            authority: authority.clone(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+        transfer(cpi_ctx, share)?;
+    }
+
+    Ok(())
+}
+
+
+/// Marks the tranches of a closed loan as paid out, their lenders can then close the positions.
+pub fn settle_tranches(tranches: &mut [Tranche]) -> Result<()> {
+    for tranche in tranches.iter_mut() {
+        tranche.position.settled = true;
+        let mut data = tranche.position_info.try_borrow_mut_data()?;
+        tranche.position.try_serialize(&mut &mut data[..])?;
+    }
+
+    Ok(())
+}
+
+
+
+/// Pays a repayment out of `payer_usdc_account`: `lender_amount` goes to the lender's USDC account,
+/// or pro rata to the tranche lenders of a syndicated loan, and the protocol `fee` to the protocol USDC vault.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_repayment<'info>(
+    token_program: &AccountInfo<'info>,
+    payer_usdc_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    lender_usdc_account: Option<&AccountInfo<'info>>,
+    protocol_vault_usdc: &AccountInfo<'info>,
+    tranches: &[Tranche<'info>],
+    lender_amount: u64,
+    fee: u64,
+) -> Result<()> {
+    if tranches.is_empty() {
+        let lender_usdc_account = lender_usdc_account.ok_or(ErrorCode::NotRightLender)?;
+        let cpi_accounts = Transfer {
+            from: payer_usdc_account.clone(),
+            to: lender_usdc_account.clone(),
+            authority: payer.clone(),
+        };
+        let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+        transfer(cpi_ctx, lender_amount)?;
+    } else {
+        transfer_usdc_to_tranches(token_program, payer_usdc_account, payer, tranches, lender_amount)?;
+    }
+
+    let cpi_accounts = Transfer {
+        from: payer_usdc_account.clone(),
+        to: protocol_vault_usdc.clone(),
+        authority: payer.clone(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.clone(), cpi_accounts);
+    transfer(cpi_ctx, fee)
+}
+
+
+/// Accounts of a repayment in full, shared by `repay_loan` and `repay_on_behalf`.
+/// The payer is the borrower or anyone repaying for them, the collateral always goes back to the borrower.
+pub struct FullRepayment<'a, 'info> {
+    pub payer: &'a AccountInfo<'info>,
+    pub payer_usdc_account: &'a Account<'info, TokenAccount>,
+    pub borrower: &'a AccountInfo<'info>,
+    pub lender_usdc_account: Option<&'a AccountInfo<'info>>,
+    pub loan_request: &'a mut Account<'info, LoanRequestState>,
+    pub collateral_vault: &'a Account<'info, CollateralVaultState>,
+    pub protocol_vault_usdc: &'a AccountInfo<'info>,
+    pub config: &'a ConfigState,
+    pub loan_registry: &'a mut LoanRegistryState,
+    pub loan_registry_page: &'a mut LoanRegistryPageState,
+    pub lending_pool: Option<&'a mut LendingPoolState>,
+    pub collateral_token_vault: Option<&'a Account<'info, TokenAccount>>,
+    pub borrower_collateral_account: Option<&'a AccountInfo<'info>>,
+    pub usdc_mint: Pubkey,
+    pub token_program: &'a AccountInfo<'info>,
+}
+
+
+/// Repays a funded loan in full until the end of its grace window: the lender is paid the outstanding principal
+/// plus lender fee or interest, late penalty and extension fees, the protocol its fee on the principal,
+/// and the collateral is released to the borrower. SOL collateral goes back when the caller closes the collateral vault.
+/// Tranche lenders of a syndicated loan come from the remaining accounts as (position, lender USDC account) pairs,
+/// the lending pool of a pool loan counts what was paid above the principal until it is settled.
+pub fn repay_in_full<'info>(repayment: FullRepayment<'_, 'info>, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+    let FullRepayment {
+        payer,
+        payer_usdc_account,
+        borrower,
+        lender_usdc_account,
+        loan_request,
+        collateral_vault,
+        protocol_vault_usdc,
+        config,
+        loan_registry,
+        loan_registry_page,
+        lending_pool,
+        collateral_token_vault,
+        borrower_collateral_account,
+        usdc_mint,
+        token_program,
+    } = repayment;
+
+    // Only a funded loan can be repaid, still possible during the grace window with a late penalty.
+    transition_loan_status(&mut loan_request.status, LoanStatus::Repaid)?;
+    check_deadline_is_not_expired(calculate_grace_deadline(loan_request)?)?;
+    check_usdc_mint_address(config.usdc_mint, usdc_mint)?;
+
+    let (lender_amount, fee, total_amount) = calculate_full_repayment_amounts(loan_request)?;
+    check_balance(payer_usdc_account.amount, total_amount)?;
+
+    let loan_request_key = loan_request.key();
+    let mut tranches = load_tranches(loan_request_key, loan_request.tranche_count, remaining_accounts, Some(usdc_mint))?;
+    transfer_repayment(
+        token_program,
+        &payer_usdc_account.to_account_info(),
+        payer,
+        lender_usdc_account,
+        protocol_vault_usdc,
+        &tranches,
+        lender_amount,
+        fee,
+    )?;
+    settle_tranches(&mut tranches)?;
+    record_pool_loan_earnings(loan_request, lending_pool, lender_amount, loan_request.outstanding_principal)?;
+
+    // SPL collateral is returned from the token vault, which is closed to the borrower.
+    if loan_request.collateral_mint.is_some() {
+        let (Some(token_vault), Some(borrower_account)) = (collateral_token_vault, borrower_collateral_account) else {
+            return Err(ErrorCode::MissingCollateralAccount.into());
+        };
+        release_token_collateral(
+            token_program,
+            &token_vault.to_account_info(),
+            borrower_account,
+            borrower,
+            &collateral_vault.to_account_info(),
+            loan_request_key,
+            collateral_vault.bump,
+            token_vault.amount,
+        )?;
+    }
+
+    remove_from_loan_registry(loan_registry, loan_registry_page, loan_request_key)?;
+    loan_request.outstanding_principal = 0;
+
+    Ok(())
+}
+
+
+/// Accounts paying out the collateral seized by `claim_collateral` or `liquidate_loan`.
+/// The token accounts are only needed for loans backed by a whitelisted mint.
+pub struct SeizedCollateral<'a, 'info> {
+    pub loan_request: &'a Account<'info, LoanRequestState>,
+    pub collateral_vault: &'a Account<'info, CollateralVaultState>,
+    pub lender: &'a AccountInfo<'info>,
+    pub borrower: &'a AccountInfo<'info>,
+    pub protocol_vault: &'a AccountInfo<'info>,
+    pub collateral_token_vault: Option<&'a Account<'info, TokenAccount>>,
+    pub lender_collateral_account: Option<&'a AccountInfo<'info>>,
+    pub borrower_collateral_account: Option<&'a AccountInfo<'info>>,
+    pub protocol_collateral_vault: Option<&'a AccountInfo<'info>>,
+    pub token_program: Option<&'a AccountInfo<'info>>,
+}
+
+
+/// Pays `lender_amount` of the seized collateral to the lender, or pro rata to the tranche lenders of a syndicated loan,
+/// and `fee` to the protocol. SOL is moved directly out of the collateral vault, whose surplus goes back to the borrower
+/// when the caller closes it. SPL collateral leaves the token vault, which returns the surplus to the borrower and is closed.
+pub fn pay_seized_collateral<'info>(
+    seized: SeizedCollateral<'_, 'info>,
+    lender_amount: u64,
+    fee: u64,
+    tranches: &[Tranche<'info>],
+) -> Result<()> {
+    let collateral_info = seized.collateral_vault.to_account_info();
+    let loan_request_key = seized.loan_request.key();
+    let bump = seized.collateral_vault.bump;
+
+    if seized.loan_request.collateral_mint.is_none() {
+        if tranches.is_empty() {
+            transfer_lamports(&collateral_info, seized.lender, lender_amount)?;
+        } else {
+            let shares = calculate_tranche_shares(tranches, lender_amount)?;
+            for (tranche, share) in tranches.iter().zip(shares) {
+                transfer_lamports(&collateral_info, &tranche.destination, share)?;
+            }
+        }
+        return transfer_lamports(&collateral_info, seized.protocol_vault, fee);
+    }
+
+    let (Some(token_vault), Some(borrower_account), Some(protocol_account), Some(token_program)) = (
+        seized.collateral_token_vault,
+        seized.borrower_collateral_account,
+        seized.protocol_collateral_vault,
+        seized.token_program,
+    ) else {
+        return Err(ErrorCode::MissingCollateralAccount.into());
+    };
+    let token_vault_info = token_vault.to_account_info();
+
+    if tranches.is_empty() {
+        let lender_account = seized.lender_collateral_account.ok_or(ErrorCode::MissingCollateralAccount)?;
+        transfer_collateral_tokens(token_program, &token_vault_info, lender_account, &collateral_info, loan_request_key, bump, lender_amount)?;
+    } else {
+        let shares = calculate_tranche_shares(tranches, lender_amount)?;
+        for (tranche, share) in tranches.iter().zip(shares) {
+            transfer_collateral_tokens(token_program, &token_vault_info, &tranche.destination, &collateral_info, loan_request_key, bump, share)?;
+        }
+    }
+    transfer_collateral_tokens(token_program, &token_vault_info, protocol_account, &collateral_info, loan_request_key, bump, fee)?;
+
+    // Anything else in the token vault, including the borrower's surplus, goes back to the borrower.
+    let remaining = token_vault.amount
+        .checked_sub(lender_amount)
+        .and_then(|x| x.checked_sub(fee))
+        .ok_or(ErrorCode::CalculationError)?;
+    release_token_collateral(
+        token_program,
+        &token_vault_info,
+        borrower_account,
+        seized.borrower,
+        &collateral_info,
+        loan_request_key,
+        bump,
+        remaining,
+    )
+}
+
+
+/// Mints a position NFT of the loan to `holder_account` and removes the mint authority, so its supply stays at 1.
+/// The mint is owned by the loan request PDA, which signs with its seeds. The NFT replaces any previous position
+/// of the loan, and the position count moves on so the next position mint gets a fresh address.
+pub fn mint_position_nft<'info>(
+    token_program: &AccountInfo<'info>,
+    position_mint: &AccountInfo<'info>,
+    holder_account: &AccountInfo<'info>,
+    loan_request: &mut Account<'info, LoanRequestState>,
+    loan_request_bump: u8,
+) -> Result<()> {
+    let borrower = loan_request.borrower;
+    let loan_id_bytes = loan_request.loan_id.to_le_bytes();
+    let loan_request_seeds: &[&[u8]] = &[
+        b"loan_request",
+        borrower.as_ref(),
+        &loan_id_bytes,
+        &[loan_request_bump],
+    ];
+    let signer_seeds = &[loan_request_seeds];
+
+    let cpi_accounts = MintTo {
+        mint: position_mint.clone(),
+        to: holder_account.clone(),
+        authority: loan_request.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+    mint_to(cpi_ctx, 1)?;
+
+    let cpi_accounts = SetAuthority {
+        current_authority: loan_request.to_account_info(),
+        account_or_mint: position_mint.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+    set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
+
+    loan_request.position_mint = Some(position_mint.key());
+    loan_request.position_count = loan_request.position_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+
+    Ok(())
+}
+
+
+/// Returns the lender owed a loan: the holder of its position NFT when `fund_loan` or `accept_loan_offer` minted one,
+/// the recorded lender otherwise. `position_account` is the holder's token account of the position mint,
+/// an account that doesn't hold the NFT resolves to no lender.
+pub fn get_loan_lender(loan_request: &LoanRequestState, position_account: Option<&TokenAccount>) -> Option<Pubkey> {
+    match loan_request.position_mint {
+        None => loan_request.lender,
+        Some(position_mint) => position_account
+            .filter(|account| account.mint == position_mint && account.amount == 1)
+            .map(|account| account.owner),
+    }
+}
+
+
+/// Address of the pool loan PDA through which the lending pool funds the loan request `loan_request_key`.
+pub fn pool_loan_address(loan_request_key: Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool_loan", loan_request_key.as_ref()], &crate::ID).0
+}
+
+
+/// Whether `lender_usdc_account` can be paid what is owed to the lender of the loan: it must belong to the lender
+/// returned by `get_loan_lender`. When that lender is a pool loan, it must be the pool loan's own USDC account,
+/// the only one `settle_pool_loan` moves into the pool vault.
+pub fn is_loan_lender_account(
+    loan_request: &Account<LoanRequestState>,
+    lender_usdc_account: &Account<TokenAccount>,
+    position_account: Option<&TokenAccount>,
+) -> bool {
+    if Some(lender_usdc_account.owner) != get_loan_lender(loan_request, position_account) {
+        return false;
+    }
+
+    let loan_request_key = loan_request.key();
+    if lender_usdc_account.owner != pool_loan_address(loan_request_key) {
+        return true;
+    }
+    let (pool_loan_usdc, _) = Pubkey::find_program_address(&[b"pool_loan_usdc", loan_request_key.as_ref()], &crate::ID);
+    lender_usdc_account.key() == pool_loan_usdc
+}
+
+
+/// Validates a lending pool policy: a minimum loan no larger than the maximum, the pool can't ask for less collateral than the protocol ratio
+/// and can't lend out more than the whole pool.
+pub fn check_pool_policy(policy: &PoolPolicy, protocol_collateral_ratio_bps: u16) -> Result<()> {
+    require!(
+        policy.min_loan_amount > 0
+            && policy.min_loan_amount <= policy.max_loan_amount
+            && policy.max_duration_days > 0
+            && policy.min_collateral_ratio_bps >= protocol_collateral_ratio_bps
+            && policy.min_collateral_ratio_bps <= MAX_COLLATERAL_RATIO_BPS
+            && policy.max_utilization_bps > 0
+            && policy.max_utilization_bps as u64 <= BPS_DENOMINATOR,
+        ErrorCode::InvalidPoolPolicy
+    );
+
+    Ok(())
+}
+
+
+/// Value of the lending pool in USDC: the `liquidity` held in the pool vault, the principal lent out,
+/// the earnings repaid to pool loans and not settled yet and the seized SOL at the oracle price.
+pub fn calculate_pool_value(lending_pool: &LendingPoolState, liquidity: u64, sol_price: &OraclePrice) -> Result<u64> {
+    let seized_value = collateral_value_usd(lending_pool.seized_collateral, SOL_DECIMALS, sol_price)?;
+
+    liquidity
+        .checked_add(lending_pool.lent_principal)
+        .and_then(|x| x.checked_add(lending_pool.unsettled_earnings))
+        .and_then(|x| x.checked_add(seized_value))
+        .ok_or(ErrorCode::CalculationError.into())
+}
+
+
+/// Moves `amount` received by a pool loan into the pool vault, signed by the pool loan PDA.
+/// The pool loan's USDC account is closed into `rent_destination` when one is given.
+#[allow(clippy::too_many_arguments)]
+pub fn sweep_pool_loan_usdc<'info>(
+    token_program: &AccountInfo<'info>,
+    pool_loan_usdc: &AccountInfo<'info>,
+    pool_usdc_vault: &AccountInfo<'info>,
+    pool_loan: &AccountInfo<'info>,
+    loan_request_key: Pubkey,
+    pool_loan_bump: u8,
+    amount: u64,
+    rent_destination: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let pool_loan_seeds: &[&[u8]] = &[b"pool_loan", loan_request_key.as_ref(), &[pool_loan_bump]];
+    let signer_seeds = &[pool_loan_seeds];
+
+    if amount > 0 {
+        transfer_loan_funds(token_program, pool_loan_usdc, pool_usdc_vault, pool_loan, signer_seeds, amount)?;
+    }
+
+    if let Some(rent_destination) = rent_destination {
+        let cpi_accounts = CloseAccount {
+            account: pool_loan_usdc.clone(),
+            destination: rent_destination.clone(),
+            authority: pool_loan.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(token_program.clone(), cpi_accounts, signer_seeds);
+        close_account(cpi_ctx)?;
+    }
+
+    Ok(())
+}
+
+
+/// Moves the pool's share of a seized collateral vault to the lending pool, where it counts in the pool value
+/// at the SOL price, and the fee to the protocol vault. The collateral vault is owned by this program,
+/// so lamports are moved directly.
+pub fn seize_pool_collateral(
+    collateral_vault: &AccountInfo,
+    lending_pool: &mut Account<LendingPoolState>,
+    protocol_vault: &AccountInfo,
+    pool_amount: u64,
+    fee: u64,
+) -> Result<()> {
+    transfer_lamports(collateral_vault, &lending_pool.to_account_info(), pool_amount)?;
+    transfer_lamports(collateral_vault, protocol_vault, fee)?;
+    lending_pool.seized_collateral = lending_pool.seized_collateral
+        .checked_add(pool_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Settles the USDC received by a pool loan into the pool vault. `unpaid_principal` is the principal the borrower
+/// hasn't repaid: it stays lent out while the loan is open and is written off once it's over, anything received
+/// above the repaid principal stops counting as unsettled earnings. When the loan is over, `rent_destination`
+/// gets the rent of the pool loan's USDC account, which is closed.
+pub fn settle_pool_loan_usdc<'info>(
+    token_program: &AccountInfo<'info>,
+    pool_loan_usdc: &Account<'info, TokenAccount>,
+    pool_usdc_vault: &AccountInfo<'info>,
+    pool_loan: &mut Account<'info, PoolLoanState>,
+    lending_pool: &mut LendingPoolState,
+    unpaid_principal: u64,
+    rent_destination: Option<&AccountInfo<'info>>,
+) -> Result<()> {
+    let repaid_principal = pool_loan.principal
+        .checked_sub(unpaid_principal)
+        .ok_or(ErrorCode::CalculationError)?;
+    // Saturating, USDC sent to the pool loan's account by anyone else lands in the pool vault as a donation.
+    let earnings = pool_loan_usdc.amount.saturating_sub(repaid_principal);
+    lending_pool.unsettled_earnings = lending_pool.unsettled_earnings.saturating_sub(earnings);
+
+    sweep_pool_loan_usdc(
+        token_program,
+        &pool_loan_usdc.to_account_info(),
+        pool_usdc_vault,
+        &pool_loan.to_account_info(),
+        pool_loan.loan_request,
+        pool_loan.bump,
+        pool_loan_usdc.amount,
+        rent_destination,
+    )?;
+    let remaining_principal = if rent_destination.is_some() { 0 } else { unpaid_principal };
+    release_pool_principal(lending_pool, pool_loan, remaining_principal)
+}
+
+
+/// Counts what a repayment pays a pool loan above `principal` in the pool value,
+/// until `settle_pool_loan` moves it into the pool vault. Loans not funded by the lending pool leave it untouched.
+pub fn record_pool_loan_earnings(
+    loan_request: &Account<LoanRequestState>,
+    lending_pool: Option<&mut LendingPoolState>,
+    lender_amount: u64,
+    principal: u64,
+) -> Result<()> {
+    if loan_request.lender != Some(pool_loan_address(loan_request.key())) {
+        return Ok(());
+    }
+
+    let lending_pool = lending_pool.ok_or(ErrorCode::MissingLendingPool)?;
+    let earnings = lender_amount
+        .checked_sub(principal)
+        .ok_or(ErrorCode::CalculationError)?;
+    lending_pool.unsettled_earnings = lending_pool.unsettled_earnings
+        .checked_add(earnings)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    Ok(())
+}
+
+
+/// Lowers the principal a pool loan still counts in the pool value down to `remaining_principal`.
+/// Called once the repaid principal or the seized collateral is back in the pool.
+pub fn release_pool_principal(
+    lending_pool: &mut LendingPoolState,
+    pool_loan: &mut PoolLoanState,
+    remaining_principal: u64,
+) -> Result<()> {
+    let released = pool_loan.principal
+        .checked_sub(remaining_principal)
+        .ok_or(ErrorCode::CalculationError)?;
+    lending_pool.lent_principal = lending_pool.lent_principal
+        .checked_sub(released)
+        .ok_or(ErrorCode::CalculationError)?;
+    pool_loan.principal = remaining_principal;
+
+    Ok(())
+}
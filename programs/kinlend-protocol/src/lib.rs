@@ -7,8 +7,10 @@ pub mod state;
 pub mod helpers;
 pub mod contexts;
 pub mod constants;
+pub mod math;
 
 pub use contexts::*;
+pub use state::{LoanTerms, PoolPolicy};
 
 #[program]
 pub mod kinlend_protocol {
@@ -18,14 +20,16 @@ pub mod kinlend_protocol {
     use super::*;
 
     //Instruction for creating loan request by borrower
+    #[allow(clippy::too_many_arguments)]
     pub fn create_loan_request(
         ctx:Context<CreateLoanRequest>, 
         loan_id:u64, 
         loan_amount:u64, 
         collateral:u64,
         duration_days:u64,
-        sol_price: u64,
-
+        page_index:u64,
+        apr_bps:Option<u16>,
+        allowed_lenders:Option<Vec<Pubkey>>,
     ) -> Result<()> {
 
         ctx.accounts.create_loan_request(
@@ -33,7 +37,9 @@ pub mod kinlend_protocol {
             loan_amount, 
             collateral,
             duration_days,
-            sol_price,
+            page_index,
+            apr_bps,
+            allowed_lenders,
             ctx.bumps
         )
 
@@ -41,30 +47,193 @@ pub mod kinlend_protocol {
     }
 
 
+    //Instruction for creating loan request backed by a whitelisted SPL token
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_loan_request_token(
+        ctx:Context<CreateLoanRequestToken>, 
+        loan_id:u64, 
+        loan_amount:u64, 
+        collateral:u64,
+        duration_days:u64,
+        page_index:u64,
+        apr_bps:Option<u16>,
+        allowed_lenders:Option<Vec<Pubkey>>,
+    ) -> Result<()> {
+        ctx.accounts.create_loan_request_token(
+            loan_id, 
+            loan_amount, 
+            collateral,
+            duration_days,
+            page_index,
+            apr_bps,
+            allowed_lenders,
+            ctx.bumps
+        )
+    }
+
+
+    //Instruction for posting a standing loan offer backed by escrowed USDC by lender
+    pub fn create_loan_offer(
+        ctx:Context<CreateLoanOffer>,
+        offer_id:u64,
+        amount:u64,
+        max_amount:u64,
+        max_duration_days:u64,
+        min_collateral_ratio_bps:u16,
+        page_index:u64,
+    ) -> Result<()> {
+        ctx.accounts.create_loan_offer(
+            offer_id,
+            amount,
+            max_amount,
+            max_duration_days,
+            min_collateral_ratio_bps,
+            page_index,
+            ctx.bumps
+        )
+    }
+
+    //Instruction for taking a loan from an offer by borrower, locking collateral and getting funded at once
+    pub fn accept_loan_offer(
+        ctx:Context<AcceptLoanOffer>,
+        _offer_id:u64,
+        loan_id:u64,
+        loan_amount:u64,
+        collateral:u64,
+        duration_days:u64,
+        page_index:u64,
+    ) -> Result<()> {
+        ctx.accounts.accept_loan_offer(
+            loan_id,
+            loan_amount,
+            collateral,
+            duration_days,
+            page_index,
+            ctx.bumps
+        )
+    }
+
+    //Instruction for withdrawing a loan offer and its remaining USDC by lender
+    pub fn cancel_loan_offer(ctx:Context<CancelLoanOffer>, _offer_id:u64) -> Result<()> {
+        ctx.accounts.cancel_loan_offer()
+    }
+
+
+    //Instruction for depositing USDC in the lending pool against pool shares
+    pub fn deposit_to_pool(ctx:Context<DepositToPool>, amount:u64) -> Result<()> {
+        ctx.accounts.deposit_to_pool(amount)
+    }
+
+    //Instruction for redeeming pool shares for USDC, up to the USDC not lent out
+    pub fn redeem_from_pool(ctx:Context<RedeemFromPool>, shares:u64) -> Result<()> {
+        ctx.accounts.redeem_from_pool(shares)
+    }
+
+    //Instruction for funding a loan request that meets the pool policy from the lending pool, callable by anyone
+    pub fn fund_from_pool(ctx:Context<FundFromPool>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.fund_from_pool(ctx.bumps)
+    }
+
+    //Instruction for moving the repayments of a pool loan into the lending pool, callable by anyone
+    pub fn settle_pool_loan(ctx:Context<SettlePoolLoan>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.settle_pool_loan()
+    }
+
+    //Instruction for claiming the collateral of a defaulted pool loan into the lending pool, callable by anyone
+    pub fn claim_pool_collateral(ctx:Context<ClaimPoolCollateral>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.claim_pool_collateral()
+    }
+
+    //Instruction for liquidating a pool loan below its liquidation threshold into the lending pool, callable by anyone
+    pub fn liquidate_pool_loan(ctx:Context<LiquidatePoolLoan>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.liquidate_pool_loan()
+    }
+
+    //Instruction for buying SOL seized by the lending pool with USDC at the oracle price
+    pub fn buy_pool_collateral(ctx:Context<BuyPoolCollateral>, lamports:u64) -> Result<()> {
+        ctx.accounts.buy_pool_collateral(lamports)
+    }
+
+
     //Instruction for cancelling Loan request by borrower
-    pub fn cancel_loan_request(ctx:Context<CancelLoanRequest>, _loan_id:u64) -> Result<()> {
-        ctx.accounts.cancel_loan_request()
+    pub fn cancel_loan_request<'info>(ctx: Context<'_, '_, '_, 'info, CancelLoanRequest<'info>>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.cancel_loan_request(ctx.remaining_accounts, ctx.bumps.loan_request)
     }
 
     //Instruction for funding loan by by lender
     pub fn fund_loan(ctx:Context<FundLoan>, _loan_id:u64) -> Result<()> {
-        ctx.accounts.fund_loan()
+        ctx.accounts.fund_loan(ctx.bumps.loan_request)
+    }
+
+    //Instruction for funding part of a loan by lender, the USDC is released once the loan is fully subscribed
+    pub fn fund_tranche(ctx:Context<FundTranche>, _loan_id:u64, amount:u64) -> Result<()> {
+        ctx.accounts.fund_tranche(amount, &ctx.bumps)
+    }
+
+    //Instruction for taking back a tranche by lender while the loan is not fully subscribed
+    pub fn withdraw_tranche(ctx:Context<WithdrawTranche>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.withdraw_tranche(ctx.bumps.loan_request)
+    }
+
+    //Instruction for closing a settled lender position by lender
+    pub fn close_lender_position(ctx:Context<CloseLenderPosition>) -> Result<()> {
+        ctx.accounts.close_lender_position()
     }
 
 
     //Instruction for repaying loan by borrower
-    pub fn repay_loan(ctx: Context<RepayLoan>, _loan_id:u64) -> Result<()> {
-        ctx.accounts.repay_loan()
+    pub fn repay_loan<'info>(ctx: Context<'_, '_, '_, 'info, RepayLoan<'info>>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.repay_loan(ctx.remaining_accounts)
+    }
+
+    //Instruction for repaying a loan by anyone on behalf of the borrower, collateral still goes to the borrower
+    pub fn repay_on_behalf<'info>(ctx: Context<'_, '_, '_, 'info, RepayOnBehalf<'info>>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.repay_on_behalf(ctx.remaining_accounts)
+    }
+
+    //Instruction for repaying part of the principal by borrower, releasing a matching share of collateral
+    pub fn repay_partial<'info>(ctx: Context<'_, '_, '_, 'info, RepayPartial<'info>>, _loan_id:u64, amount:u64) -> Result<()> {
+        ctx.accounts.repay_partial(amount, ctx.remaining_accounts)
+    }
+
+    //Instruction for refinancing a funded loan, a new lender pays off the current one and the borrower agrees to new terms
+    pub fn refinance_loan<'info>(ctx: Context<'_, '_, '_, 'info, RefinanceLoan<'info>>, _loan_id:u64, duration_days:u64, apr_bps:Option<u16>) -> Result<()> {
+        ctx.accounts.refinance_loan(duration_days, apr_bps, ctx.bumps.loan_request, ctx.remaining_accounts)
+    }
+
+    //Instruction for proposing a longer duration and an optional fee by borrower
+    pub fn propose_loan_extension(ctx: Context<ProposeLoanExtension>, _loan_id:u64, duration_days:u64, fee:Option<u64>) -> Result<()> {
+        ctx.accounts.propose_loan_extension(duration_days, fee)
+    }
+
+    //Instruction for accepting the proposed extension by lender
+    pub fn accept_loan_extension(ctx: Context<AcceptLoanExtension>, _loan_id:u64, duration_days:u64, fee:u64) -> Result<()> {
+        ctx.accounts.accept_loan_extension(duration_days, fee)
+    }
+
+    //Instruction for forgiving a loan by lender, releasing all collateral to the borrower
+    pub fn forgive_loan(ctx: Context<ForgiveLoan>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.forgive_loan()
+    }
+
+    //Instruction for topping up the collateral of a loan by borrower
+    pub fn add_collateral(ctx: Context<AddCollateral>, _loan_id:u64, amount:u64) -> Result<()> {
+        ctx.accounts.add_collateral(amount)
+    }
+
+    //Instruction for withdrawing collateral above the initial collateral ratio by borrower
+    pub fn withdraw_excess_collateral(ctx: Context<WithdrawExcessCollateral>, _loan_id:u64, amount:u64) -> Result<()> {
+        ctx.accounts.withdraw_excess_collateral(amount)
     }
 
     //Instruction for claiming collateral by Lender if Borrower fails to repay
-    pub fn claim_collateral(ctx:Context<ClaimCollateral>, _loan_id:u64) -> Result<()> {
-        ctx.accounts.claim_collateral()
+    pub fn claim_collateral<'info>(ctx: Context<'_, '_, '_, 'info, ClaimCollateral<'info>>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.claim_collateral(ctx.remaining_accounts)
     }
 
     //Instruction for liquidating Loan
-    pub fn liquidate_loan(ctx: Context<LiquidateLoan>, _loan_id:u64, sol_price: u64) -> Result<()> {
-        ctx.accounts.liquidate_loan(sol_price)
+    pub fn liquidate_loan<'info>(ctx: Context<'_, '_, '_, 'info, LiquidateLoan<'info>>, _loan_id:u64) -> Result<()> {
+        ctx.accounts.liquidate_loan(ctx.remaining_accounts)
     }
 
     //Instruction for creating Protocol Vault which for receiving SOL as FEE
@@ -77,9 +246,44 @@ pub mod kinlend_protocol {
         ctx.accounts.init_config()
     }
 
-    //ADMIN ONLY: instruction for updating usdc mint key 
-    pub fn update_config(ctx:Context<UpdateConfig>) -> Result<()> {
-        ctx.accounts.update_config()
+    //ADMIN ONLY: instruction for updating usdc mint key, loan terms, treasury and forgiveness fee
+    pub fn update_config(ctx:Context<UpdateConfig>, new_terms: Option<LoanTerms>, new_treasury: Option<Pubkey>, new_forgiveness_fee: Option<u64>) -> Result<()> {
+        ctx.accounts.update_config(new_terms, new_treasury, new_forgiveness_fee)
+    }
+
+    //ADMIN ONLY: instruction for withdrawing SOL fees from protocol vault to treasury
+    pub fn withdraw_protocol_fees(ctx:Context<WithdrawProtocolFees>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_protocol_fees(amount)
+    }
+
+    //ADMIN ONLY: instruction for withdrawing USDC fees from protocol vault to treasury
+    pub fn withdraw_protocol_fees_usdc(ctx:Context<WithdrawProtocolFeesUsdc>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_protocol_fees_usdc(amount, ctx.bumps.protocol_vault_authority)
+    }
+
+    //ADMIN ONLY: instruction for whitelisting an SPL mint as collateral with its oracle feed and haircut
+    pub fn whitelist_collateral_mint(ctx:Context<WhitelistCollateralMint>, feed_id: [u8; 32], haircut_bps: u16) -> Result<()> {
+        ctx.accounts.whitelist_collateral_mint(feed_id, haircut_bps, ctx.bumps)
+    }
+
+    //ADMIN ONLY: instruction for updating the feed, haircut or status of a whitelisted collateral mint
+    pub fn update_collateral_mint(ctx:Context<UpdateCollateralMint>, feed_id: [u8; 32], haircut_bps: u16, enabled: bool) -> Result<()> {
+        ctx.accounts.update_collateral_mint(feed_id, haircut_bps, enabled)
+    }
+
+    //ADMIN ONLY: instruction for withdrawing fees taken in SPL collateral to treasury
+    pub fn withdraw_protocol_fees_token(ctx:Context<WithdrawProtocolFeesToken>, amount: u64) -> Result<()> {
+        ctx.accounts.withdraw_protocol_fees_token(amount, ctx.bumps.protocol_vault_authority)
+    }
+
+    //ADMIN ONLY: instruction for creating the lending pool, its share mint and USDC vault
+    pub fn create_lending_pool(ctx:Context<CreateLendingPool>, policy: PoolPolicy) -> Result<()> {
+        ctx.accounts.create_lending_pool(policy, ctx.bumps)
+    }
+
+    //ADMIN ONLY: instruction for updating the policy of the lending pool
+    pub fn update_pool_policy(ctx:Context<UpdatePoolPolicy>, policy: PoolPolicy) -> Result<()> {
+        ctx.accounts.update_pool_policy(policy)
     }
 
     //Instruction for creating LoanRegistry which store Loan Requests
@@ -87,6 +291,21 @@ pub mod kinlend_protocol {
         ctx.accounts.create_loan_registry()
     }
 
+    //Instruction for adding a page to the LoanRegistry once existing pages are full
+    pub fn create_loan_registry_page(ctx:Context<CreateLoanRegistryPage>) -> Result<()> {
+        ctx.accounts.create_loan_registry_page(ctx.bumps)
+    }
+
+    //Instruction for creating OfferRegistry which store Loan Offers
+    pub fn create_offer_registry(ctx:Context<CreateOfferRegistry>) -> Result<()> {
+        ctx.accounts.create_offer_registry()
+    }
+
+    //Instruction for adding a page to the OfferRegistry once existing pages are full
+    pub fn create_offer_registry_page(ctx:Context<CreateOfferRegistryPage>) -> Result<()> {
+        ctx.accounts.create_offer_registry_page(ctx.bumps)
+    }
+
     
 
 }
@@ -0,0 +1,436 @@
+use anchor_lang::prelude::*;
+
+use crate::constants::{BPS_DENOMINATOR, POOL_VIRTUAL_ASSETS, POOL_VIRTUAL_SHARES, SECONDS_PER_YEAR, USDC_DECIMALS};
+use crate::errors::ErrorCode;
+
+// Checked fixed-point arithmetic shared by the loan instructions.
+// Every intermediate value is computed in u128 so no f64 is ever used on-chain.
+
+/// Oracle price as read from Pyth: the USD value of one whole token is `price * 10^exponent`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OraclePrice {
+    pub price: u64,
+    pub exponent: i32,
+}
+
+/// Computes `a * b / c` rounded down.
+pub fn mul_div(a: u64, b: u64, c: u64) -> Result<u64> {
+    let result = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div(c as u128)
+        .ok_or(ErrorCode::CalculationError)?;
+    to_u64(result)
+}
+
+/// Computes `a * b / c` rounded up.
+pub fn mul_div_ceil(a: u64, b: u64, c: u64) -> Result<u64> {
+    let numerator = (a as u128)
+        .checked_mul(b as u128)
+        .ok_or(ErrorCode::CalculationError)?;
+    to_u64(div_ceil(numerator, c as u128)?)
+}
+
+/// Applies a basis-point rate to an amount, rounded down (e.g. 10_400 bps = 104%).
+pub fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    mul_div(amount, bps, BPS_DENOMINATOR)
+}
+
+/// USD value (USDC smallest unit) of `amount` base units of a token with `decimals`, rounded down.
+pub fn collateral_value_usd(amount: u64, decimals: u32, oracle_price: &OraclePrice) -> Result<u64> {
+    let (numerator, denominator) = price_scale(decimals, oracle_price)?;
+    let value = (amount as u128)
+        .checked_mul(numerator)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div(denominator)
+        .ok_or(ErrorCode::CalculationError)?;
+    to_u64(value)
+}
+
+/// Minimum collateral (token base units) so that its USD value is at least
+/// `ratio_bps` of `usd_amount`. Rounded up so the protocol never under-collateralizes.
+pub fn required_collateral(
+    usd_amount: u64,
+    ratio_bps: u64,
+    decimals: u32,
+    oracle_price: &OraclePrice,
+) -> Result<u64> {
+    let (numerator, denominator) = price_scale(decimals, oracle_price)?;
+    let required = (usd_amount as u128)
+        .checked_mul(ratio_bps as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_mul(denominator)
+        .ok_or(ErrorCode::CalculationError)?;
+    let divisor = numerator
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::CalculationError)?;
+    to_u64(div_ceil(required, divisor)?)
+}
+
+/// Returns true when the USD value of `amount` is strictly below `threshold_bps` of `usd_amount`.
+/// Compared exactly by cross-multiplying, without rounding either side.
+pub fn is_below_threshold(
+    amount: u64,
+    decimals: u32,
+    oracle_price: &OraclePrice,
+    usd_amount: u64,
+    threshold_bps: u64,
+) -> Result<bool> {
+    let (numerator, denominator) = price_scale(decimals, oracle_price)?;
+    let collateral_side = (amount as u128)
+        .checked_mul(numerator)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::CalculationError)?;
+    let debt_side = (usd_amount as u128)
+        .checked_mul(threshold_bps as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_mul(denominator)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok(collateral_side < debt_side)
+}
+
+/// Simple interest on `principal` at `apr_bps` per year over `elapsed_seconds`, rounded down.
+pub fn accrued_interest(principal: u64, apr_bps: u16, elapsed_seconds: u64) -> Result<u64> {
+    let interest = (principal as u128)
+        .checked_mul(apr_bps as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_mul(elapsed_seconds as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div((BPS_DENOMINATOR as u128) * (SECONDS_PER_YEAR as u128))
+        .ok_or(ErrorCode::CalculationError)?;
+    to_u64(interest)
+}
+
+/// End of the grace window of a loan due at `repayment_time`, `grace_period_days` later.
+pub fn grace_deadline(repayment_time: i64, grace_period_days: u16) -> Result<i64> {
+    let grace_seconds = (grace_period_days as i64)
+        .checked_mul(86_400)
+        .ok_or(ErrorCode::Overflow)?;
+    repayment_time
+        .checked_add(grace_seconds)
+        .ok_or(ErrorCode::Overflow.into())
+}
+
+/// Days late at `now` for a loan due at `repayment_time`, every started day counts in full.
+/// Nothing is late up to and including the deadline.
+pub fn late_days(repayment_time: i64, now: i64) -> u64 {
+    if now <= repayment_time {
+        return 0;
+    }
+    ((now - repayment_time) as u64).div_ceil(86_400)
+}
+
+/// Late penalty on `principal` at `now`: `penalty_bps_per_day` for every started day after `repayment_time`.
+pub fn late_penalty(principal: u64, repayment_time: i64, now: i64, penalty_bps_per_day: u16) -> Result<u64> {
+    let penalty_bps = late_days(repayment_time, now)
+        .checked_mul(penalty_bps_per_day as u64)
+        .ok_or(ErrorCode::CalculationError)?;
+    apply_bps(principal, penalty_bps)
+}
+
+/// Discounts an oracle price by `haircut_bps`, rounded down (e.g. 500 bps values collateral at 95%).
+pub fn apply_haircut(oracle_price: &OraclePrice, haircut_bps: u16) -> Result<OraclePrice> {
+    let remaining_bps = BPS_DENOMINATOR
+        .checked_sub(haircut_bps as u64)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok(OraclePrice {
+        price: apply_bps(oracle_price.price, remaining_bps)?,
+        exponent: oracle_price.exponent,
+    })
+}
+
+/// Splits seized collateral into `(lender_amount, protocol_fee, borrower_amount)`:
+/// - the lender receives `lender_claim` (collateral worth the debt plus premium), capped at the collateral,
+/// - the protocol fee is `fee_bps` of the lender amount, capped at what is left,
+/// - any surplus goes back to the borrower.
+pub fn split_seized_collateral(collateral: u64, lender_claim: u64, fee_bps: u64) -> Result<(u64, u64, u64)> {
+    let lender_amount = collateral.min(lender_claim);
+    let remaining = collateral
+        .checked_sub(lender_amount)
+        .ok_or(ErrorCode::CalculationError)?;
+    let protocol_fee = apply_bps(lender_amount, fee_bps)?.min(remaining);
+    let borrower_amount = remaining
+        .checked_sub(protocol_fee)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok((lender_amount, protocol_fee, borrower_amount))
+}
+
+/// Splits `amount` across shares pro rata to their `weights`, rounded down.
+/// The last share takes the rounding remainder so the parts always add up to `amount`.
+pub fn split_pro_rata(amount: u64, weights: &[u64]) -> Result<Vec<u64>> {
+    let total_weight = weights
+        .iter()
+        .try_fold(0u64, |acc, &weight| acc.checked_add(weight))
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let mut parts = Vec::with_capacity(weights.len());
+    let mut allocated = 0u64;
+    for (index, &weight) in weights.iter().enumerate() {
+        let part = if index + 1 == weights.len() {
+            amount.checked_sub(allocated).ok_or(ErrorCode::CalculationError)?
+        } else {
+            mul_div(amount, weight, total_weight)?
+        };
+        allocated = allocated.checked_add(part).ok_or(ErrorCode::CalculationError)?;
+        parts.push(part);
+    }
+    Ok(parts)
+}
+
+/// Collateral held by a collateral vault of `vault_lamports`, checked against the `collateral` recorded on the loan.
+/// The vault's `rent_exempt` deposit is never counted as collateral, and neither are lamports sent to it directly.
+pub fn vault_collateral(vault_lamports: u64, rent_exempt: u64, collateral: u64) -> Result<u64> {
+    require!(collateral > 0, ErrorCode::NoCollateral);
+
+    let required_lamports = rent_exempt
+        .checked_add(collateral)
+        .ok_or(ErrorCode::CalculationError)?;
+    require!(vault_lamports >= required_lamports, ErrorCode::NoCollateral);
+
+    Ok(collateral)
+}
+
+/// Pool shares minted for a deposit of `amount` USDC into a pool worth `pool_value`, rounded down.
+/// Shares are priced with virtual shares and USDC on both sides, so the first deposit mints shares one for one,
+/// value sitting in a pool with no shares isn't handed to its first depositor and a pool worth nothing still
+/// prices new deposits.
+pub fn shares_for_deposit(amount: u64, share_supply: u64, pool_value: u64) -> Result<u64> {
+    let (virtual_supply, virtual_value) = virtual_pool(share_supply, pool_value)?;
+    mul_div(amount, virtual_supply, virtual_value)
+}
+
+/// USDC owed for redeeming `shares` out of `share_supply`, rounded down, at the same virtual price as deposits.
+/// Capped at the shares' pro-rata part of the pool value, which the virtual USDC would exceed in a pool
+/// worth less than one unit per share.
+pub fn assets_for_shares(shares: u64, share_supply: u64, pool_value: u64) -> Result<u64> {
+    let (virtual_supply, virtual_value) = virtual_pool(share_supply, pool_value)?;
+    let assets = mul_div(shares, virtual_value, virtual_supply)?;
+    Ok(assets.min(mul_div(shares, pool_value, share_supply)?))
+}
+
+/// Share supply and pool value including the virtual shares and USDC.
+fn virtual_pool(share_supply: u64, pool_value: u64) -> Result<(u64, u64)> {
+    let virtual_supply = share_supply
+        .checked_add(POOL_VIRTUAL_SHARES)
+        .ok_or(ErrorCode::CalculationError)?;
+    let virtual_value = pool_value
+        .checked_add(POOL_VIRTUAL_ASSETS)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok((virtual_supply, virtual_value))
+}
+
+/// Splits the conversion `amount * price * 10^(exponent + USDC_DECIMALS - decimals)`
+/// into an integer numerator and denominator.
+fn price_scale(decimals: u32, oracle_price: &OraclePrice) -> Result<(u128, u128)> {
+    require!(oracle_price.price > 0, ErrorCode::InvalidOracleAccount);
+
+    let scale = oracle_price
+        .exponent
+        .checked_add(USDC_DECIMALS as i32)
+        .and_then(|x| x.checked_sub(decimals as i32))
+        .ok_or(ErrorCode::CalculationError)?;
+    let power = 10u128
+        .checked_pow(scale.unsigned_abs())
+        .ok_or(ErrorCode::CalculationError)?;
+    let price = oracle_price.price as u128;
+
+    if scale >= 0 {
+        Ok((price.checked_mul(power).ok_or(ErrorCode::CalculationError)?, 1))
+    } else {
+        Ok((price, power))
+    }
+}
+
+fn div_ceil(numerator: u128, denominator: u128) -> Result<u128> {
+    require!(denominator > 0, ErrorCode::CalculationError);
+    numerator
+        .checked_add(denominator - 1)
+        .map(|x| x / denominator)
+        .ok_or(ErrorCode::CalculationError.into())
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| ErrorCode::CalculationError.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::SOL_DECIMALS;
+
+    // $200 per SOL as Pyth publishes it (exponent -8)
+    const SOL_200: OraclePrice = OraclePrice { price: 20_000_000_000, exponent: -8 };
+
+    #[test]
+    fn mul_div_rounds_down_and_up() {
+        assert_eq!(mul_div(10, 1, 3).unwrap(), 3);
+        assert_eq!(mul_div_ceil(10, 1, 3).unwrap(), 4);
+        assert_eq!(mul_div_ceil(9, 1, 3).unwrap(), 3);
+        assert!(mul_div(1, 1, 0).is_err());
+    }
+
+    #[test]
+    fn mul_div_handles_u64_max_intermediates() {
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX).unwrap(), u64::MAX);
+        assert!(mul_div(u64::MAX, 2, 1).is_err());
+    }
+
+    #[test]
+    fn apply_bps_matches_percentages() {
+        assert_eq!(apply_bps(1_000_000, 10_400).unwrap(), 1_040_000);
+        assert_eq!(apply_bps(1_000_000, 100).unwrap(), 10_000);
+        assert_eq!(apply_bps(99, 100).unwrap(), 0);
+    }
+
+    #[test]
+    fn collateral_value_handles_exponents() {
+        // 1 SOL at $200
+        assert_eq!(collateral_value_usd(1_000_000_000, SOL_DECIMALS, &SOL_200).unwrap(), 200_000_000);
+        // Same price expressed with a positive scale
+        let price = OraclePrice { price: 200, exponent: 0 };
+        assert_eq!(collateral_value_usd(1_000_000_000, SOL_DECIMALS, &price).unwrap(), 200_000_000);
+        // A token with more decimals than the quote
+        let price = OraclePrice { price: 2, exponent: 3 };
+        assert_eq!(collateral_value_usd(1, 0, &price).unwrap(), 2_000_000_000);
+    }
+
+    #[test]
+    fn required_collateral_rounds_up() {
+        // $1 at 150% and $200/SOL = 0.0075 SOL exactly
+        assert_eq!(required_collateral(1_000_000, 15_000, SOL_DECIMALS, &SOL_200).unwrap(), 7_500_000);
+        // One extra micro-USD needs one more lamport, never one less
+        assert_eq!(required_collateral(1_000_001, 15_000, SOL_DECIMALS, &SOL_200).unwrap(), 7_500_008);
+    }
+
+    #[test]
+    fn threshold_is_strict_at_the_boundary() {
+        // 0.0055 SOL at $200 = $1.10, exactly 110% of $1
+        assert!(!is_below_threshold(5_500_000, SOL_DECIMALS, &SOL_200, 1_000_000, 11_000).unwrap());
+        assert!(is_below_threshold(5_499_999, SOL_DECIMALS, &SOL_200, 1_000_000, 11_000).unwrap());
+    }
+
+    #[test]
+    fn threshold_is_exact_for_large_loans() {
+        // $1T loan, collateral worth exactly 110% of it
+        let loan = 1_000_000_000_000_000_000;
+        let collateral = 5_500_000_000_000_000_000;
+        assert!(!is_below_threshold(collateral, SOL_DECIMALS, &SOL_200, loan, 11_000).unwrap());
+        assert!(is_below_threshold(collateral - 1, SOL_DECIMALS, &SOL_200, loan, 11_000).unwrap());
+    }
+
+    #[test]
+    fn split_returns_surplus_to_borrower() {
+        // lender is owed 1_000, 10% fee, 3_000 of collateral
+        assert_eq!(split_seized_collateral(3_000, 1_000, 1_000).unwrap(), (1_000, 100, 1_900));
+    }
+
+    #[test]
+    fn split_caps_lender_and_fee_at_collateral() {
+        // under-collateralized: lender takes everything, nothing left for fee or borrower
+        assert_eq!(split_seized_collateral(900, 1_000, 1_000).unwrap(), (900, 0, 0));
+        // fee is capped at what is left after the lender
+        assert_eq!(split_seized_collateral(1_050, 1_000, 1_000).unwrap(), (1_000, 50, 0));
+    }
+
+    #[test]
+    fn pro_rata_split_gives_remainder_to_last() {
+        assert_eq!(split_pro_rata(1_040_000, &[500_000, 500_000]).unwrap(), vec![520_000, 520_000]);
+        // 100 split 1:1:1, the last share picks up the lost unit
+        assert_eq!(split_pro_rata(100, &[1, 1, 1]).unwrap(), vec![33, 33, 34]);
+        assert_eq!(split_pro_rata(0, &[3, 7]).unwrap(), vec![0, 0]);
+        assert!(split_pro_rata(1, &[0, 0]).is_err());
+    }
+
+    #[test]
+    fn pool_shares_follow_pool_value() {
+        // first deposit mints one for one
+        assert_eq!(shares_for_deposit(1_000_000, 0, 0).unwrap(), 1_000_000);
+        // after 10% of gains, the same deposit buys fewer shares
+        assert_eq!(shares_for_deposit(2_100_000, 1_000_000, 1_100_000).unwrap(), 2_000_000);
+        assert_eq!(assets_for_shares(500_000, 2_000_000, 2_300_000).unwrap(), 550_000);
+        // rounding always favours the shares left in the pool
+        assert_eq!(shares_for_deposit(1, 2, 1_000_000).unwrap(), 0);
+        assert_eq!(assets_for_shares(1, 3, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn pool_shares_resist_empty_pool_edge_cases() {
+        // value sitting in an empty pool stays with the virtual shares, the first depositor gets back their deposit
+        let shares = shares_for_deposit(1_000_000, 0, 1_000_000).unwrap();
+        assert_eq!(shares, 500_000);
+        assert_eq!(assets_for_shares(shares, shares, 2_000_000).unwrap(), 1_000_000);
+        // after a 1 unit deposit and a 1 USDC donation, a victim's deposit still redeems in full
+        let shares = shares_for_deposit(1_000_000, 1, 1_000_001).unwrap();
+        assert_eq!(shares, 500_000);
+        assert_eq!(assets_for_shares(shares, shares + 1, 2_000_001).unwrap(), 999_999);
+        // a pool that lost everything still takes deposits, at the virtual price, and its shares redeem for nothing
+        assert_eq!(shares_for_deposit(1_000_000, 1_000_000, 0).unwrap(), 2_000_000);
+        assert_eq!(assets_for_shares(1_000_000, 1_000_000, 0).unwrap(), 0);
+        // an underwater pool pays its pro-rata value, not the virtual USDC on top
+        assert_eq!(assets_for_shares(1_000_000, 1_000_000, 500_000).unwrap(), 500_000);
+        assert_eq!(assets_for_shares(250_000, 1_000_000, 500_000).unwrap(), 125_000);
+    }
+
+    #[test]
+    fn vault_collateral_excludes_rent() {
+        // 0.0075 SOL of collateral on top of a rent deposit of 946_560 lamports
+        let rent_exempt = 946_560;
+        assert_eq!(vault_collateral(rent_exempt + 7_500_000, rent_exempt, 7_500_000).unwrap(), 7_500_000);
+        // one lamport short means part of the rent would be paid out as collateral
+        assert!(vault_collateral(rent_exempt + 7_499_999, rent_exempt, 7_500_000).is_err());
+        // lamports sent to the vault directly are not collateral
+        assert_eq!(vault_collateral(rent_exempt + 9_000_000, rent_exempt, 7_500_000).unwrap(), 7_500_000);
+        // a vault without collateral has nothing to settle
+        assert!(vault_collateral(rent_exempt, rent_exempt, 0).is_err());
+    }
+
+    #[test]
+    fn interest_accrues_per_second() {
+        // 10% APR on 1_000 USDC for a full year, half a year and one day
+        let principal = 1_000_000_000;
+        assert_eq!(accrued_interest(principal, 1_000, SECONDS_PER_YEAR).unwrap(), 100_000_000);
+        assert_eq!(accrued_interest(principal, 1_000, SECONDS_PER_YEAR / 2).unwrap(), 50_000_000);
+        assert_eq!(accrued_interest(principal, 1_000, 86_400).unwrap(), 273_972);
+        assert_eq!(accrued_interest(principal, 1_000, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn grace_window_follows_the_deadline() {
+        let deadline = 1_700_000_000;
+        assert_eq!(grace_deadline(deadline, 3).unwrap(), deadline + 3 * 86_400);
+        assert_eq!(grace_deadline(deadline, 0).unwrap(), deadline);
+        assert!(grace_deadline(i64::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn late_penalty_counts_started_days() {
+        // 0.5% per day on 1 USDC
+        let deadline = 1_700_000_000;
+        assert_eq!(late_penalty(1_000_000, deadline, deadline - 1, 50).unwrap(), 0);
+        // exactly at the deadline is still on time
+        assert_eq!(late_days(deadline, deadline), 0);
+        assert_eq!(late_penalty(1_000_000, deadline, deadline, 50).unwrap(), 0);
+        // one second late starts the first day
+        assert_eq!(late_days(deadline, deadline + 1), 1);
+        assert_eq!(late_penalty(1_000_000, deadline, deadline + 1, 50).unwrap(), 5_000);
+        // a whole day late is still one day, one more second starts the second
+        assert_eq!(late_days(deadline, deadline + 86_400), 1);
+        assert_eq!(late_days(deadline, deadline + 86_401), 2);
+        assert_eq!(late_penalty(1_000_000, deadline, deadline + 86_401, 50).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn haircut_discounts_price() {
+        let price = apply_haircut(&SOL_200, 500).unwrap();
+        assert_eq!(collateral_value_usd(1_000_000_000, SOL_DECIMALS, &price).unwrap(), 190_000_000);
+        assert_eq!(apply_haircut(&SOL_200, 0).unwrap(), SOL_200);
+        assert!(apply_haircut(&SOL_200, 10_001).is_err());
+    }
+
+    #[test]
+    fn rejects_non_positive_price() {
+        let price = OraclePrice { price: 0, exponent: -8 };
+        assert!(collateral_value_usd(1, SOL_DECIMALS, &price).is_err());
+    }
+}
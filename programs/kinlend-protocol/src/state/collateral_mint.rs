@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+// Whitelisted SPL collateral mint (mSOL, jitoSOL, wBTC...) and how it is priced.
+#[account]
+#[derive(InitSpace)]
+pub struct CollateralMintState {
+    pub mint: Pubkey,           // SPL mint accepted as collateral
+    pub feed_id: [u8; 32],      // Pyth feed id pricing one whole token in USD
+    pub haircut_bps: u16,       // discount applied to the oracle value of the collateral
+    pub decimals: u8,           // decimals of the mint
+    pub enabled: bool,          // only enabled mints can back new loans
+    pub bump: u8,
+}
@@ -1,8 +1,13 @@
 use anchor_lang::prelude::*;
 
+use super::LoanTerms;
+
 #[account]
 #[derive(InitSpace)]
 pub struct ConfigState{
     pub usdc_mint: Pubkey, //stores correcr usdc mint address
     pub authority: Pubkey, //stores wallet address of admin
+    pub treasury: Pubkey, //receives protocol fees withdrawn from the vaults
+    pub terms: LoanTerms, //terms applied to newly created loan requests
+    pub forgiveness_fee: u64, //flat USDC fee paid by a lender forgiving a loan, 0 to skip it
 }
\ No newline at end of file
@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+
+// Tranche put up by one lender of a syndicated loan.
+// Repayments and seized collateral are split across positions pro rata to `amount`.
+#[account]
+#[derive(InitSpace)]
+pub struct LenderPositionState {
+    pub loan_request: Pubkey,   // Loan request this tranche funds
+    pub lender: Pubkey,         // Lender owning the tranche
+    pub amount: u64,            // USDC put up by the lender
+    pub settled: bool,          // Set once the loan is closed and the tranche paid out or refunded
+    pub bump: u8,
+}
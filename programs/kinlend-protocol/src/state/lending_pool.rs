@@ -0,0 +1,23 @@
+use anchor_lang::prelude::*;
+
+// Protocol-managed USDC pool lending to requests that meet its policy.
+// Depositors hold shares of the pool value: USDC in the pool vault, principal lent out, earnings not settled yet and seized SOL.
+#[account]
+#[derive(InitSpace)]
+pub struct LendingPoolState {
+    pub lent_principal: u64,      // Principal of pool loans not settled back into the pool vault yet
+    pub unsettled_earnings: u64,  // Lender fees and interest repaid to pool loans and not settled into the pool vault yet
+    pub seized_collateral: u64,   // Lamports seized from defaulted pool loans, valued at the SOL price
+    pub policy: PoolPolicy,       // Requests the pool is allowed to fund
+    pub bump: u8,
+}
+
+// Limits applied by the pool to every request it funds.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct PoolPolicy {
+    pub min_loan_amount: u64,           // Smallest single loan in USDC
+    pub max_loan_amount: u64,           // Largest single loan in USDC
+    pub max_duration_days: u64,         // Longest loan duration
+    pub min_collateral_ratio_bps: u16,  // Collateral asked for at funding (15_000 = 150%, a max LTV of ~66%)
+    pub max_utilization_bps: u16,       // Share of the pool value that can be lent out at once (8_000 = 80%)
+}
@@ -0,0 +1,16 @@
+use anchor_lang::prelude::*;
+
+// Standing offer posted by a lender, backed by USDC held in the offer escrow.
+// A borrower taking the offer locks collateral and is funded in the same transaction.
+#[account]
+#[derive(InitSpace)]
+pub struct LoanOfferState {
+    pub offer_id: u64,
+    pub lender: Pubkey,                 // Lender funding the loans taken from the offer
+    pub max_amount: u64,                // Largest loan in USDC a borrower can take from the offer
+    pub available_amount: u64,          // Escrowed USDC left to lend
+    pub max_duration_days: u64,         // Longest duration a borrower can choose
+    pub min_collateral_ratio_bps: u16,  // Collateral required by the lender, at least the protocol ratio
+    pub registry_page: u64,             // Index of the offer registry page listing this offer
+    pub bump: u8,
+}
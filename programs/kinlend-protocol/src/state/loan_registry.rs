@@ -1,10 +1,20 @@
 use anchor_lang::prelude::*;
 
-// The LoanRegistry account is a public directory of active loan requests.
+// The LoanRegistry root account is a header over the registry pages.
 #[account]
 #[derive(InitSpace)]
 pub struct LoanRegistryState {
+    pub page_count: u64, //number of registry pages created so far
+    pub total_loans: u64, //tracking total numbers of active loan requests.
+    pub free_slots: u64, //empty slots left across all pages
+}
+
+// A registry page is a public directory of up to LOAN_REGISTRY_PAGE_SIZE active loan requests.
+#[account]
+#[derive(InitSpace)]
+pub struct LoanRegistryPageState {
+    pub page_index: u64, //index used in the page seeds
     #[max_len(20)]
     pub loan_requests: Vec<Pubkey>, //stores loan request pubkeys
-    pub total_loans: u64, //tracking total numbers of active loan requests.
-}
\ No newline at end of file
+    pub bump: u8,
+}
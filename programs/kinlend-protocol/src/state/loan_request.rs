@@ -1,13 +1,52 @@
 use anchor_lang::prelude::*;
 
+use super::LoanTerms;
+
 #[account]
 #[derive(InitSpace)]
 pub struct LoanRequestState {
     pub loan_id: u64,
     pub borrower: Pubkey,       // Borrower's wallet address
     pub lender: Option<Pubkey>, // Address of the lender
+    #[max_len(5)]
+    pub allowed_lenders: Vec<Pubkey>, // Lenders allowed to fund a private request, empty for anyone
     pub loan_amount: u64,       // Desired loan amount in USDC
-    pub collateral: u64,        // Collateral in SOL (must be at least 150% of loan amount in USDC value)
-    pub duration_days: u64,     // Loan duration in days (set by borrower)
+    pub outstanding_principal: u64, // Principal left to repay in USDC, lowered by partial repayments
+    pub apr_bps: Option<u16>,   // Yearly interest accruing per second, None for the flat lender fee
+    pub collateral: u64,        // Collateral in lamports or token base units (must be at least 150% of loan amount in USDC value)
+    pub collateral_mint: Option<Pubkey>, // Whitelisted SPL collateral mint, None for native SOL
+    pub collateral_haircut_bps: u16,     // Haircut on the collateral value, snapshotted from the mint config
+    pub duration_days: u64,     // Loan duration in days (set by borrower, raised by accepted extensions)
     pub repayment_time: Option<i64>,    // Unix Timestamp when the lender funds the loan
+    pub terms: LoanTerms,       // Protocol terms in force when the request was created
+    pub status: LoanStatus,     // Lifecycle state of the loan
+    pub registry_page: u64,     // Index of the registry page listing this request
+    pub pending_extension: Option<LoanExtension>, // Extension proposed by the borrower, waiting for the lender
+    pub extension_count: u16,   // Number of extensions accepted by the lender
+    pub extension_fees: u64,    // Extension fees in USDC owed to the lender on top of the repayment
+    pub subscribed_amount: u64, // USDC put up by tranche lenders, held in escrow until the loan is fully subscribed
+    pub tranche_count: u16,     // Open lender positions of a syndicated loan, 0 when funded by a single lender
+    pub position_mint: Option<Pubkey>, // Position NFT minted by fund_loan, its holder is owed the loan instead of `lender`
+    pub position_count: u16,    // Position NFTs minted for the loan so far, seeds the next position mint
+}
+
+// Extension of a funded loan proposed by the borrower.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct LoanExtension {
+    pub duration_days: u64,     // New total duration in days, counted from funding
+    pub fee: u64,               // Fee in USDC paid to the lender with the repayment
+}
+
+// Lifecycle of a loan request:
+// Requested -> Funded | Cancelled
+// Funded -> Repaid | Defaulted | Liquidated | Forgiven
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum LoanStatus {
+    Requested,
+    Funded,
+    Repaid,
+    Defaulted,
+    Liquidated,
+    Cancelled,
+    Forgiven,
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+// Protocol parameters in basis points (10_000 = 100%), plus the grace window in days.
+// ConfigState holds the current terms, each loan keeps a copy taken at creation.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, InitSpace)]
+pub struct LoanTerms {
+    pub collateral_ratio_bps: u16,      // collateral required at creation (15_000 = 150%)
+    pub liquidation_threshold_bps: u16, // collateral value below which the loan can be liquidated (11_000 = 110%)
+    pub lender_fee_bps: u16,            // premium paid to the lender on repayment (400 = 4%)
+    pub protocol_fee_bps: u16,          // fee paid to the protocol on repayment (100 = 1%)
+    pub claim_fee_bps: u16,             // share of claimed collateral kept by the protocol (1_000 = 10%)
+    pub liquidation_fee_bps: u16,       // share of liquidated collateral kept by the protocol (182 ~= 2/110)
+    pub grace_period_days: u16,         // days after the deadline the borrower can still repay before a claim (3)
+    pub late_penalty_bps_per_day: u16,  // penalty on the principal paid to the lender per day late (50 = 0.5%)
+}
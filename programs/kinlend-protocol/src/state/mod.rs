@@ -3,9 +3,23 @@ pub mod collateral_vault;
 pub mod protocol_vault;
 pub mod loan_registry;
 pub mod config;
+pub mod loan_terms;
+pub mod collateral_mint;
+pub mod lender_position;
+pub mod loan_offer;
+pub mod offer_registry;
+pub mod lending_pool;
+pub mod pool_loan;
 
 pub use loan_request::*;
 pub use collateral_vault::*;
 pub use protocol_vault::*;
 pub use loan_registry::*;
-pub use config::*;
\ No newline at end of file
+pub use config::*;
+pub use loan_terms::*;
+pub use collateral_mint::*;
+pub use lender_position::*;
+pub use loan_offer::*;
+pub use offer_registry::*;
+pub use lending_pool::*;
+pub use pool_loan::*;
\ No newline at end of file
@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+// The OfferRegistry root account is a header over the offer registry pages.
+#[account]
+#[derive(InitSpace)]
+pub struct OfferRegistryState {
+    pub page_count: u64, //number of offer registry pages created so far
+    pub total_offers: u64, //tracking total numbers of open loan offers.
+    pub free_slots: u64, //empty slots left across all pages
+}
+
+// An offer registry page is a public directory of up to OFFER_REGISTRY_PAGE_SIZE open loan offers.
+#[account]
+#[derive(InitSpace)]
+pub struct OfferRegistryPageState {
+    pub page_index: u64, //index used in the page seeds
+    #[max_len(20)]
+    pub loan_offers: Vec<Pubkey>, //stores loan offer pubkeys
+    pub bump: u8,
+}
@@ -0,0 +1,12 @@
+use anchor_lang::prelude::*;
+
+// Loan funded by the lending pool. Its PDA is recorded as the lender of the loan request,
+// so repayments land in the pool loan's own USDC account until they are settled into the pool vault.
+#[account]
+#[derive(InitSpace)]
+pub struct PoolLoanState {
+    pub loan_request: Pubkey,   // Loan request funded by the pool
+    pub principal: u64,         // Principal still counted in the pool's lent principal
+    pub keeper: Pubkey,         // Account that paid the rent of the pool loan accounts, refunded on settlement
+    pub bump: u8,
+}
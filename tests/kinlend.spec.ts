@@ -2,9 +2,10 @@ import * as anchor from "@coral-xyz/anchor";
 import { BN, Program } from "@coral-xyz/anchor";
 import { KinlendProtocol } from "../target/types/kinlend_protocol";
 import { Keypair, LAMPORTS_PER_SOL, PublicKey } from "@solana/web3.js";
-import { createMint, getOrCreateAssociatedTokenAccount, mintTo, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { createAccount, createMint, getOrCreateAssociatedTokenAccount, mintTo, transfer, TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import NodeWallet from "@coral-xyz/anchor/dist/cjs/nodewallet";
 import { SYSTEM_PROGRAM_ID } from "@coral-xyz/anchor/dist/cjs/native/system";
+import { MockPythPull } from "@tkkinn/mock-pyth-sdk";
 import { assert, expect } from "chai";
 
 describe("KINLEND PROTOCOL", () => {
@@ -16,6 +17,10 @@ describe("KINLEND PROTOCOL", () => {
   // Test values
   let loanId = new BN(1);
   const SOL_PRICE = 200_000_000; // $200 with 6 decimals
+  const SOL_USD_FEED_ID = "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+
+  // Pyth pull-oracle price update account (mock receiver program loaded by solana-local-validator.sh)
+  let solPriceUpdate: PublicKey;
 
   // Test accounts
   let borrower = Keypair.generate();
@@ -47,6 +52,43 @@ describe("KINLEND PROTOCOL", () => {
     [Buffer.from("loan_registry")],
     program.programId
   );
+
+  const [loanRegistryPagePDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("loan_registry_page"), new BN(0).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+
+  const [offerRegistryPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("offer_registry")],
+    program.programId
+  );
+
+  const [offerRegistryPagePDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("offer_registry_page"), new BN(0).toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+
+  // Lending pool, its share mint and its USDC vault
+  const [lendingPoolPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("lending_pool")],
+    program.programId
+  );
+  const [shareMintPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_share_mint")],
+    program.programId
+  );
+  const [poolUsdcVaultPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("pool_usdc_vault")],
+    program.programId
+  );
+
+  // Position NFT number `nonce` of a loan, minted by fund_loan, and the token account holding it for `holder`
+  const positionMintOf = (loanRequest: PublicKey, nonce = 0) => PublicKey.findProgramAddressSync(
+    [Buffer.from("position_mint"), loanRequest.toBuffer(), new BN(nonce).toArrayLike(Buffer, "le", 2)],
+    program.programId
+  )[0];
+  const positionTokenAccountOf = (loanRequest: PublicKey, holder: PublicKey, nonce = 0) =>
+    anchor.utils.token.associatedAddress({ mint: positionMintOf(loanRequest, nonce), owner: holder });
   
  
   const [loanRequestPDA] = PublicKey.findProgramAddressSync(
@@ -73,6 +115,46 @@ describe("KINLEND PROTOCOL", () => {
     program.programId
   );
 
+  // Posts a SOL/USD PriceUpdateV2 account with the given price (6 decimals, exponent -6)
+  const postSolPrice = async (price: number): Promise<PublicKey> => {
+    const mockPythPull = new MockPythPull(provider);
+    return mockPythPull.createPriceUpdate({
+      feedId: SOL_USD_FEED_ID,
+      price: new BN(price),
+      conf: new BN(0),
+      exponent: -6,
+    });
+  };
+
+  // Lets the validator clock move past a deadline
+  const sleep = (ms: number) => new Promise(resolve => setTimeout(resolve, ms));
+
+  // Loan terms set by init_config, and the admin call replacing them for loans created afterwards
+  const defaultTerms = {
+    collateralRatioBps: 15_000,
+    liquidationThresholdBps: 11_000,
+    lenderFeeBps: 400,
+    protocolFeeBps: 100,
+    claimFeeBps: 1_000,
+    liquidationFeeBps: 182,
+    gracePeriodDays: 3,
+    latePenaltyBpsPerDay: 50,
+  };
+  const updateTerms = (terms: typeof defaultTerms) => program.methods
+    .updateConfig(terms, null, null)
+    .accountsPartial({
+      admin,
+      config: configPDA,
+      newUsdcMint: null,
+      systemProgram: SYSTEM_PROGRAM_ID
+    })
+    .signers([adminPayer])
+    .rpc();
+
+  before(async() => {
+    solPriceUpdate = await postSolPrice(SOL_PRICE);
+  });
+
   // Set up test environment before each test
   beforeEach(async() => {
     // Airdrop SOL to borrower and lender for transaction fees
@@ -139,12 +221,64 @@ describe("KINLEND PROTOCOL", () => {
       // Verify the loan registry was initialized correctly
       const loanRegistry = await program.account.loanRegistryState.fetch(loanRegistryPDA);
       expect(loanRegistry.totalLoans.toString()).to.equal(new BN(0).toString());
+      expect(loanRegistry.pageCount.toNumber()).to.equal(0);
     } catch(err) {
       console.error("Error initializing loan registry:", err);
       assert.fail("Failed to initialize loan registry");
     }
   });
 
+  // Test 2b: Initialize the first Loan Registry page
+  it("Should create the first Loan Registry page", async() => {
+    await program.methods
+      .createLoanRegistryPage()
+      .accountsPartial({
+        payer: admin,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([adminPayer])
+      .rpc();
+
+    const loanRegistry = await program.account.loanRegistryState.fetch(loanRegistryPDA);
+    expect(loanRegistry.pageCount.toNumber()).to.equal(1);
+    expect(loanRegistry.freeSlots.toNumber()).to.equal(20);
+
+    const loanRegistryPage = await program.account.loanRegistryPageState.fetch(loanRegistryPagePDA);
+    expect(loanRegistryPage.pageIndex.toNumber()).to.equal(0);
+    expect(loanRegistryPage.loanRequests).to.have.length(0);
+  });
+
+  // Test 2c: Initialize the Offer Registry and its first page
+  it("Should initialize the Offer Registry and its first page", async() => {
+    await program.methods
+      .createOfferRegistry()
+      .accountsPartial({
+        admin,
+        offerRegistry: offerRegistryPDA,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([adminPayer])
+      .rpc();
+
+    await program.methods
+      .createOfferRegistryPage()
+      .accountsPartial({
+        payer: admin,
+        offerRegistry: offerRegistryPDA,
+        offerRegistryPage: offerRegistryPagePDA,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([adminPayer])
+      .rpc();
+
+    const offerRegistry = await program.account.offerRegistryState.fetch(offerRegistryPDA);
+    expect(offerRegistry.pageCount.toNumber()).to.equal(1);
+    expect(offerRegistry.freeSlots.toNumber()).to.equal(20);
+    expect(offerRegistry.totalOffers.toNumber()).to.equal(0);
+  });
+
   // Test 3: Initialize Config
   it("Should initialize config PDA account", async() => {
 
@@ -176,6 +310,10 @@ describe("KINLEND PROTOCOL", () => {
       console.log(`config usdc: ${configUsdcMint.toBase58()}, usdcMint: ${usdcMint.toBase58()}` )
       expect(config.authority.toBase58()).to.equal(admin.toBase58());
       expect(config.usdcMint.toBase58()).to.equal(usdcMint.toBase58());
+      expect(config.terms.collateralRatioBps).to.equal(15_000);
+      expect(config.terms.liquidationThresholdBps).to.equal(11_000);
+      expect(config.terms.gracePeriodDays).to.equal(3);
+      expect(config.terms.latePenaltyBpsPerDay).to.equal(50);
     } catch(err) {
       console.error("Error initializing config:", err);
       assert.fail("Failed to initialize config");
@@ -189,7 +327,7 @@ describe("KINLEND PROTOCOL", () => {
     try {
       // Attempt to update config as non-admin (lender)
       await program.methods
-        .updateConfig()
+        .updateConfig(null, null, null)
         .accountsPartial({
           admin: lender.publicKey,
           config: configPDA,
@@ -205,6 +343,58 @@ describe("KINLEND PROTOCOL", () => {
     }
   });
 
+  // Test 5b: Loan terms outside of sanity bounds are rejected
+  it("Should reject loan terms with a liquidation threshold above the collateral ratio", async() => {
+    try {
+      await program.methods
+        .updateConfig({
+          collateralRatioBps: 11_000,
+          liquidationThresholdBps: 15_000,
+          lenderFeeBps: 400,
+          protocolFeeBps: 100,
+          claimFeeBps: 1_000,
+          liquidationFeeBps: 182,
+          gracePeriodDays: 3,
+          latePenaltyBpsPerDay: 50,
+        }, null, null)
+        .accountsPartial({
+          admin,
+          config: configPDA,
+          newUsdcMint: null,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([adminPayer])
+        .rpc();
+
+      assert.fail("Updating config with invalid terms did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidConfigParameter");
+    }
+  });
+
+  // Test 5c: Grace period and late penalty bounds
+  it("Should reject a grace period or a late penalty above their maximum", async() => {
+    // MAX_GRACE_PERIOD_DAYS = 30, MAX_LATE_PENALTY_BPS_PER_DAY = 500
+    for (const terms of [
+      { ...defaultTerms, gracePeriodDays: 31 },
+      { ...defaultTerms, latePenaltyBpsPerDay: 501 },
+    ]) {
+      try {
+        await updateTerms(terms);
+        assert.fail("Updating config with terms above their bounds did not fail as expected");
+      } catch(err) {
+        expect(err.toString()).to.include("InvalidConfigParameter");
+      }
+    }
+
+    // The bounds themselves are accepted
+    await updateTerms({ ...defaultTerms, gracePeriodDays: 30, latePenaltyBpsPerDay: 500 });
+    await updateTerms(defaultTerms);
+    const config = await program.account.configState.fetch(configPDA);
+    expect(config.terms.gracePeriodDays).to.equal(3);
+    expect(config.terms.latePenaltyBpsPerDay).to.equal(50);
+  });
+
   // Test 6: Cannot reinitialize config
   it("Should fail to reinitialize config PDA account", async() => {
     try {
@@ -250,13 +440,18 @@ describe("KINLEND PROTOCOL", () => {
           loanAmount,
           collateral,
           noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
         )
         .accountsPartial({
           borrower: borrower.publicKey,
           loanRequest: loanRequestPDA,
           collateralVault: collateralVaultPDA,
           loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
           systemProgram: SYSTEM_PROGRAM_ID
         })
         .signers([borrower])
@@ -271,22 +466,66 @@ describe("KINLEND PROTOCOL", () => {
       expect(loanRequestAccount.borrower.toBase58()).to.equal(borrower.publicKey.toBase58());
       expect(loanRequestAccount.lender).to.equal(null);
       expect(loanRequestAccount.repaymentTime).to.equal(null);
+      expect(loanRequestAccount.status).to.deep.equal({ requested: {} });
 
       // Verify collateral vault was created correctly
       const collateralVaultAccount = await program.account.collateralVaultState.fetch(collateralVaultPDA);
       expect(collateralVaultAccount.bump).to.equal(collateralVaultPDABump);
 
       // Verify loan registry was updated
-      const loanRegistryAccount = await program.account.loanRegistryState.fetch(loanRegistryPDA);
-      expect(loanRegistryAccount.loanRequests.map((pk) => pk.toBase58()))
+      const loanRegistryPageAccount = await program.account.loanRegistryPageState.fetch(loanRegistryPagePDA);
+      expect(loanRegistryPageAccount.loanRequests.map((pk) => pk.toBase58()))
         .to.include(loanRequestPDA.toBase58());
+      expect(loanRequestAccount.registryPage.toNumber()).to.equal(0);
+      const loanRegistryAccount = await program.account.loanRegistryState.fetch(loanRegistryPDA);
       expect(loanRegistryAccount.totalLoans.toNumber()).to.equal(1);
+      expect(loanRegistryAccount.freeSlots.toNumber()).to.equal(19);
     } catch (error) {
       console.error("Error creating loan request:", error);
       assert.fail("Failed to create loan request");
     }
   });
 
+  it("Should reject a loan request without an amount or a duration", async() => {
+    const emptyLoanId = new BN(99);
+    const [emptyLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), emptyLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [emptyCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), emptyLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const createEmptyRequest = (loanAmount: BN, durationDays: BN) => program.methods
+      .createLoanRequest(emptyLoanId, loanAmount, new BN(7_500_000), durationDays, new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: emptyLoanRequestPDA,
+        collateralVault: emptyCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    try {
+      await createEmptyRequest(new BN(0), new BN(30));
+      assert.fail("Creating a loan request of 0 USDC did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidAmount");
+    }
+
+    try {
+      await createEmptyRequest(new BN(1_000_000), new BN(0));
+      assert.fail("Creating a loan request of 0 days did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidDuration");
+    }
+  });
+
   // Test 8: Fund Loan
   it("Should fund a loan request", async() => {
     // Create loan request first
@@ -312,13 +551,18 @@ describe("KINLEND PROTOCOL", () => {
           loanAmount,
           collateral,
           noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
         )
         .accountsStrict({
           borrower: borrower.publicKey,
           loanRequest: newLoanRequestPDA,
           collateralVault: newCollateralVaultPDA,
           loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
           systemProgram: SYSTEM_PROGRAM_ID
         })
         .signers([borrower])
@@ -357,6 +601,8 @@ describe("KINLEND PROTOCOL", () => {
           lender: lender.publicKey,
           config: configPDA,
           loanRequest: newLoanRequestPDA,
+          positionMint: positionMintOf(newLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
           borrower: borrower.publicKey,
           lenderUsdcAccount: lenderUsdcATA,
           borrowerUsdcAccount: borrowerUsdcATA,
@@ -374,6 +620,7 @@ describe("KINLEND PROTOCOL", () => {
       expect(loanRequestAccount.lender).to.not.equal(null);
       expect(loanRequestAccount.lender.toString()).to.equal(lender.publicKey.toString());
       expect(loanRequestAccount.repaymentTime).to.not.equal(null);
+      expect(loanRequestAccount.status).to.deep.equal({ funded: {} });
 
       // Verify borrower received USDC
       const borrowerBalance = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
@@ -412,13 +659,18 @@ describe("KINLEND PROTOCOL", () => {
           loanAmount,
           collateral,
           noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
         )
         .accountsStrict({
           borrower: borrower.publicKey,
           loanRequest: newLoanRequestPDA,
           collateralVault: newCollateralVaultPDA,
           loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
           systemProgram: SYSTEM_PROGRAM_ID
         })
         .signers([borrower])
@@ -456,6 +708,8 @@ describe("KINLEND PROTOCOL", () => {
           lender: lender.publicKey,
           config: configPDA,
           loanRequest: newLoanRequestPDA,
+          positionMint: positionMintOf(newLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
           borrower: borrower.publicKey,
           lenderUsdcAccount: lenderUsdcATA,
           borrowerUsdcAccount: borrowerUsdcATA,
@@ -489,18 +743,50 @@ describe("KINLEND PROTOCOL", () => {
         program.programId
       );
 
-      // Repay the loan
+      // Repay half of the principal first, releasing half of the collateral
+      await program.methods
+        .repayPartial(newLoanId, new BN(500_000))
+        .accountsPartial({
+          lendingPool: null, // not a pool loan
+          borrower: borrower.publicKey,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          lenderUsdcAccount: lenderUsdcATA,
+          loanRequest: newLoanRequestPDA,
+          positionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
+          collateralVault: newCollateralVaultPDA,
+          protocolVaultUsdc: protocolVaultUsdcPDA,
+          protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+          config: configPDA,
+          collateralTokenVault: null, // SOL-backed loan
+          borrowerCollateralAccount: null,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+
+      const partiallyRepaid = await program.account.loanRequestState.fetch(newLoanRequestPDA);
+      expect(partiallyRepaid.outstandingPrincipal.toNumber()).to.equal(500_000);
+      expect(partiallyRepaid.collateral.toNumber()).to.equal(collateral.toNumber() / 2);
+      expect(partiallyRepaid.status).to.deep.equal({ funded: {} });
+
+      // Repay the remaining balance
       await program.methods
         .repayLoan(newLoanId)
         .accountsPartial({
+          lendingPool: null, // not a pool loan
+          collateralTokenVault: null, // SOL-backed loan
           borrower: borrower.publicKey,
           borrowerUsdcAccount: borrowerUsdcATA,
           lenderUsdcAccount: lenderUsdcATA,
           loanRequest: newLoanRequestPDA,
+          positionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
           collateralVault: newCollateralVaultPDA,
           protocolVaultUsdc: protocolVaultUsdcPDA,
           protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
           config: configPDA,
+          loanRegistryPage: loanRegistryPagePDA,
           usdcMint,
           tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SYSTEM_PROGRAM_ID,
@@ -524,14 +810,10 @@ describe("KINLEND PROTOCOL", () => {
       // The borrower should have their original balance minus transaction fees plus returned collateral
       expect(borrowerSolBalance).to.be.greaterThan(9 * LAMPORTS_PER_SOL);
 
-      // Try to fetch the loan request account - should fail as it's closed
-      try {
-        await program.account.loanRequestState.fetch(newLoanRequestPDA);
-        assert.fail("Loan request account should be closed");
-      } catch (error) {
-        // Just verify that an error was thrown, don't check the specific message
-        assert.ok(true, "Error thrown as expected when fetching closed account");
-      }
+      // The loan request is kept as a record and marked as repaid
+      const loanRequestAccount = await program.account.loanRequestState.fetch(newLoanRequestPDA);
+      expect(loanRequestAccount.status).to.deep.equal({ repaid: {} });
+      expect(loanRequestAccount.outstandingPrincipal.toNumber()).to.equal(0);
       
     } catch (error) {
       console.error("Error repaying loan:", error);
@@ -539,172 +821,1788 @@ describe("KINLEND PROTOCOL", () => {
     }
   });
 
-  // Test 10: Claim Collateral After Deadline
-  it("Should claim collateral after loan deadline", async() => {
-    // Create and fund a loan that will expire
-    const expiredLoanId = new BN(20);
-    const loanAmount = new BN(1_000_000); // 1 USDC
-    const collateral = new BN(7_500_000); // 0.0075 SOL
-    const noOfDays = new BN(30);
-    const SOL_PRICE = 200_000_000; // $200 with 6 decimals
-    
-    // Use a different loan ID for this test
-    
-    const [expiredLoanRequestPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), expiredLoanId.toArrayLike(Buffer, "le", 8)],
+  // Test 9b: Repayment must go to the recorded lender
+  it("Should reject repaying into a token account not owned by the lender", async() => {
+    const newLoanId = new BN(11);
+    const [newLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), newLoanId.toArrayLike(Buffer, "le", 8)],
       program.programId
     );
-    
-    const [expiredCollateralVaultPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("collateral_vault"), expiredLoanRequestPDA.toBuffer()],
+    const [newCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), newLoanRequestPDA.toBuffer()],
       program.programId
     );
 
-    try {
-      // Create loan request
-      await program.methods
-        .createLoanRequest(
-          expiredLoanId,
-          loanAmount,
-          collateral,
-          noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
-        )
-        .accountsPartial({
-          borrower: borrower.publicKey,
-          loanRequest: expiredLoanRequestPDA,
-          collateralVault: expiredCollateralVaultPDA,
-          loanRegistry: loanRegistryPDA,
-          systemProgram: SYSTEM_PROGRAM_ID
-        })
-        .signers([borrower])
-        .rpc();
-
-      // Fund the loan
-      await program.methods
-        .fundLoan(expiredLoanId)
-        .accountsPartial({
-          lender: lender.publicKey,
-          config: configPDA,
-          loanRequest: expiredLoanRequestPDA,
-          borrower: borrower.publicKey,
-          lenderUsdcAccount: lenderUsdcATA,
-          borrowerUsdcAccount: borrowerUsdcATA,
-          usdcMint,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          systemProgram: SYSTEM_PROGRAM_ID
-        })
-        .signers([lender])
-        .rpc();
+    await program.methods
+      .createLoanRequest(newLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: newLoanRequestPDA,
+        collateralVault: newCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    await program.methods
+      .fundLoan(newLoanId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        config: configPDA,
+        loanRequest: newLoanRequestPDA,
+        positionMint: positionMintOf(newLoanRequestPDA),
+        lenderPositionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
+        borrower: borrower.publicKey,
+        lenderUsdcAccount: lenderUsdcATA,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 1_050_000);
+
+    // A second USDC account controlled by the borrower
+    const borrowerSecondUsdcAccount = await createAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      borrower.publicKey,
+      Keypair.generate()
+    );
 
-      // NOTE: In a real test environment, we would need to manipulate time
-      // Since we can't do that in this environment, we'll just note that
-      // this test would normally fail with LoanIsNotExpired error
-      
-      // For demonstration purposes, we'll try to claim collateral (expecting it to fail)
+    for (const wrongLenderAccount of [borrowerSecondUsdcAccount, borrowerUsdcATA]) {
       try {
         await program.methods
-          .claimCollateral(expiredLoanId)
+          .repayLoan(newLoanId)
           .accountsPartial({
-            lender: lender.publicKey,
-            loanRequest: expiredLoanRequestPDA,
-            collateralVault: expiredCollateralVaultPDA,
-            protocolVault: protocolVaultPDA,
-            loanRegistry: loanRegistryPDA,
-            systemProgram: SYSTEM_PROGRAM_ID
+            lendingPool: null, // not a pool loan
+            collateralTokenVault: null, // SOL-backed loan
+            borrower: borrower.publicKey,
+            borrowerUsdcAccount: borrowerUsdcATA,
+            lenderUsdcAccount: wrongLenderAccount,
+            loanRequest: newLoanRequestPDA,
+            positionTokenAccount: positionTokenAccountOf(newLoanRequestPDA, lender.publicKey),
+            collateralVault: newCollateralVaultPDA,
+            protocolVaultUsdc: protocolVaultUsdcPDA,
+            protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+            config: configPDA,
+            loanRegistryPage: loanRegistryPagePDA,
+            usdcMint,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SYSTEM_PROGRAM_ID,
+            rent: anchor.web3.SYSVAR_RENT_PUBKEY
           })
-          .signers([lender])
+          .signers([borrower])
           .rpc();
-        
-        // If we reach here, the test should fail
-        assert.fail("Should not be able to claim collateral before deadline");
+        assert.fail("Repaying into a non-lender account did not fail as expected");
       } catch (error) {
-        // Expected error since we can't manipulate time in this test environment
-        console.log("Expected error:", error.toString());
+        expect(error.toString()).to.include("NotRightLender");
       }
-    } catch (error) {
-      console.error("Error in claim collateral test:", error);
-      throw error;
     }
+
+    // Collateral is still locked and the loan is still funded
+    const loanRequestAccount = await program.account.loanRequestState.fetch(newLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ funded: {} });
+    const vaultBalance = await provider.connection.getBalance(newCollateralVaultPDA);
+    expect(vaultBalance).to.be.greaterThan(7_500_000);
   });
 
-  // Test 11: Liquidate Loan
-  it("Should liquidate a loan when collateral value drops", async() => {
-    // Create and fund a loan
-    const loanAmount = new BN(1_000_000); // 1 USDC
-    const collateral = new BN(7_500_000); // 0.0075 SOL
-    const noOfDays = new BN(30);
-    const SOL_PRICE = 200_000_000; // $200 with 6 decimals
-    
-    // Use a different loan ID for this test
-    const liquidationLoanId = new BN(30);
-    
-    const [liquidationLoanRequestPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), liquidationLoanId.toArrayLike(Buffer, "le", 8)],
+  // Test 9c: Lender-approved extension
+  it("Should extend a funded loan once the lender accepts the proposal", async() => {
+    // Loan 11 is funded for 30 days by test 9b
+    const extendedLoanId = new BN(11);
+    const [extendedLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), extendedLoanId.toArrayLike(Buffer, "le", 8)],
       program.programId
     );
-    
-    const [liquidationCollateralVaultPDA] = PublicKey.findProgramAddressSync(
-      [Buffer.from("collateral_vault"), liquidationLoanRequestPDA.toBuffer()],
+    const before = await program.account.loanRequestState.fetch(extendedLoanRequestPDA);
+
+    // Borrower proposes 60 days in total and a 0.01 USDC fee
+    await program.methods
+      .proposeLoanExtension(extendedLoanId, new BN(60), new BN(10_000))
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: extendedLoanRequestPDA,
+      })
+      .signers([borrower])
+      .rpc();
+
+    const accept = (fee: BN) => program.methods
+      .acceptLoanExtension(extendedLoanId, new BN(60), fee)
+      .accountsPartial({
+        lender: lender.publicKey,
+        loanRequest: extendedLoanRequestPDA,
+        positionTokenAccount: positionTokenAccountOf(extendedLoanRequestPDA, lender.publicKey),
+      })
+      .signers([lender])
+      .rpc();
+
+    // Accepting terms other than the proposed ones is rejected
+    try {
+      await accept(new BN(0));
+      assert.fail("Accepting different terms did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidExtension");
+    }
+
+    await accept(new BN(10_000));
+
+    const after = await program.account.loanRequestState.fetch(extendedLoanRequestPDA);
+    expect(after.durationDays.toNumber()).to.equal(60);
+    expect(after.repaymentTime.sub(before.repaymentTime).toNumber()).to.equal(30 * 86_400);
+    expect(after.extensionCount).to.equal(1);
+    expect(after.extensionFees.toNumber()).to.equal(10_000);
+    expect(after.pendingExtension).to.equal(null);
+  });
+
+  // Test 9d: Forgive Loan
+  it("Should let only the lender forgive a loan and return the collateral", async() => {
+    // Loan 11 is funded by test 9b and extended by test 9c
+    const forgivenLoanId = new BN(11);
+    const [forgivenLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), forgivenLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [forgivenCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), forgivenLoanRequestPDA.toBuffer()],
       program.programId
     );
 
+    const forgive = (signer: Keypair) => program.methods
+      .forgiveLoan(forgivenLoanId)
+      .accountsPartial({
+        lender: signer.publicKey,
+        borrower: borrower.publicKey,
+        loanRequest: forgivenLoanRequestPDA,
+        positionTokenAccount: positionTokenAccountOf(forgivenLoanRequestPDA, lender.publicKey),
+        collateralVault: forgivenCollateralVaultPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        lenderUsdcAccount: null, // no forgiveness fee configured
+        protocolVaultUsdc: null,
+        collateralTokenVault: null, // SOL-backed loan
+        borrowerCollateralAccount: null,
+        tokenProgram: null,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([signer])
+      .rpc();
+
+    // The borrower cannot forgive their own loan
     try {
-      // Create loan request
-      await program.methods
-        .createLoanRequest(
-          liquidationLoanId,
-          loanAmount,
-          collateral,
-          noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
-        )
-        .accountsPartial({
-          borrower: borrower.publicKey,
-          loanRequest: liquidationLoanRequestPDA,
-          collateralVault: liquidationCollateralVaultPDA,
-          loanRegistry: loanRegistryPDA,
-          systemProgram: SYSTEM_PROGRAM_ID
-        })
-        .signers([borrower])
-        .rpc();
+      await forgive(borrower);
+      assert.fail("Forgiving as the borrower did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("UnauthorizedLender");
+    }
 
-      // Fund the loan
+    const borrowerBalanceBefore = await provider.connection.getBalance(borrower.publicKey);
+    await forgive(lender);
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(forgivenLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ forgiven: {} });
+    expect(loanRequestAccount.outstandingPrincipal.toNumber()).to.equal(0);
+
+    // Collateral and the vault's rent deposit are back with the borrower
+    const borrowerBalanceAfter = await provider.connection.getBalance(borrower.publicKey);
+    expect(borrowerBalanceAfter - borrowerBalanceBefore).to.be.greaterThanOrEqual(7_500_000);
+    expect(await provider.connection.getAccountInfo(forgivenCollateralVaultPDA)).to.equal(null);
+  });
+
+  // Test 9e: Repay on behalf of the borrower
+  it("Should let a guarantor repay while the collateral goes back to the borrower", async() => {
+    const guaranteedLoanId = new BN(12);
+    const guarantor = Keypair.generate();
+    const [guaranteedLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), guaranteedLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [guaranteedCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), guaranteedLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    const airdropSig = await provider.connection.requestAirdrop(guarantor.publicKey, LAMPORTS_PER_SOL);
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({
+      signature: airdropSig,
+      blockhash: latestBlockhash.blockhash,
+      lastValidBlockHeight: latestBlockhash.lastValidBlockHeight,
+    });
+
+    await program.methods
+      .createLoanRequest(guaranteedLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: guaranteedLoanRequestPDA,
+        collateralVault: guaranteedCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    await program.methods
+      .fundLoan(guaranteedLoanId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        config: configPDA,
+        loanRequest: guaranteedLoanRequestPDA,
+        positionMint: positionMintOf(guaranteedLoanRequestPDA),
+        lenderPositionTokenAccount: positionTokenAccountOf(guaranteedLoanRequestPDA, lender.publicKey),
+        borrower: borrower.publicKey,
+        lenderUsdcAccount: lenderUsdcATA,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const guarantorUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      guarantor.publicKey
+    ).then(acc => acc.address);
+    await mintTo(provider.connection, adminPayer, usdcMint, guarantorUsdcAccount, admin, 1_050_000);
+
+    const borrowerBalanceBefore = await provider.connection.getBalance(borrower.publicKey);
+    const guarantorBalanceBefore = await provider.connection.getBalance(guarantor.publicKey);
+
+    await program.methods
+      .repayOnBehalf(guaranteedLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        payer: guarantor.publicKey,
+        borrower: borrower.publicKey,
+        payerUsdcAccount: guarantorUsdcAccount,
+        lenderUsdcAccount: lenderUsdcATA,
+        loanRequest: guaranteedLoanRequestPDA,
+        positionTokenAccount: positionTokenAccountOf(guaranteedLoanRequestPDA, lender.publicKey),
+        collateralVault: guaranteedCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        borrowerCollateralAccount: null,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([guarantor])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(guaranteedLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ repaid: {} });
+
+    // The guarantor paid the USDC, the borrower got the collateral back
+    const guarantorUsdcBalance = await provider.connection.getTokenAccountBalance(guarantorUsdcAccount);
+    expect(guarantorUsdcBalance.value.amount).to.equal("0");
+    const borrowerBalanceAfter = await provider.connection.getBalance(borrower.publicKey);
+    expect(borrowerBalanceAfter - borrowerBalanceBefore).to.be.greaterThanOrEqual(7_500_000);
+    const guarantorBalanceAfter = await provider.connection.getBalance(guarantor.publicKey);
+    expect(guarantorBalanceAfter).to.be.lessThanOrEqual(guarantorBalanceBefore);
+  });
+
+  // Test 9f: Private loan request
+  it("Should only let an allowed lender fund a private loan request", async() => {
+    const privateLoanId = new BN(13);
+    const outsider = Keypair.generate();
+    const [privateLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), privateLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [privateCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), privateLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    const airdropSig = await provider.connection.requestAirdrop(outsider.publicKey, LAMPORTS_PER_SOL);
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({
+      signature: airdropSig,
+      blockhash: latestBlockhash.blockhash,
+      lastValidBlockHeight: latestBlockhash.lastValidBlockHeight,
+    });
+
+    // Only the lender is allowed to fund this request
+    await program.methods
+      .createLoanRequest(privateLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0), null, [lender.publicKey])
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: privateLoanRequestPDA,
+        collateralVault: privateCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(privateLoanRequestPDA);
+    expect(loanRequestAccount.allowedLenders.map((key) => key.toBase58())).to.deep.equal([lender.publicKey.toBase58()]);
+
+    const outsiderUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      outsider.publicKey
+    ).then(acc => acc.address);
+    await mintTo(provider.connection, adminPayer, usdcMint, outsiderUsdcAccount, admin, 1_000_000);
+
+    try {
       await program.methods
-        .fundLoan(liquidationLoanId)
+        .fundLoan(privateLoanId)
         .accountsPartial({
-          lender: lender.publicKey,
+          lender: outsider.publicKey,
           config: configPDA,
-          loanRequest: liquidationLoanRequestPDA,
+          loanRequest: privateLoanRequestPDA,
+          positionMint: positionMintOf(privateLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(privateLoanRequestPDA, outsider.publicKey),
           borrower: borrower.publicKey,
-          lenderUsdcAccount: lenderUsdcATA,
+          lenderUsdcAccount: outsiderUsdcAccount,
           borrowerUsdcAccount: borrowerUsdcATA,
           usdcMint,
           tokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SYSTEM_PROGRAM_ID
         })
-        .signers([lender])
+        .signers([outsider])
         .rpc();
+      assert.fail("Funding by a lender outside the allowlist did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("UnauthorizedLender");
+    }
 
-      // Now we'll simulate a price drop by passing a lower SOL price to liquidate_loan
-      // In real markets, SOL price would drop from $200 to $100
-      const loweredSolPrice = 100_000_000; // $100 with 6 decimals
-      
-      // Try to liquidate the loan with the new lower price
+    await program.methods
+      .cancelLoanRequest(privateLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: privateLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        trancheEscrow: null, // no tranches to refund
+      })
+      .signers([borrower])
+      .rpc();
+  });
+
+  // Test 9g: Syndicated loan
+  it("Should fund a loan in tranches and repay each lender pro rata", async() => {
+    const syndicatedLoanId = new BN(14);
+    const secondLender = Keypair.generate();
+    const [syndicatedLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), syndicatedLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [syndicatedCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), syndicatedLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [trancheEscrowPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("tranche_escrow"), syndicatedLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [lenderPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lender_position"), syndicatedLoanRequestPDA.toBuffer(), lender.publicKey.toBuffer()],
+      program.programId
+    );
+    const [secondLenderPositionPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("lender_position"), syndicatedLoanRequestPDA.toBuffer(), secondLender.publicKey.toBuffer()],
+      program.programId
+    );
+
+    const airdropSig = await provider.connection.requestAirdrop(secondLender.publicKey, LAMPORTS_PER_SOL);
+    const latestBlockhash = await provider.connection.getLatestBlockhash();
+    await provider.connection.confirmTransaction({
+      signature: airdropSig,
+      blockhash: latestBlockhash.blockhash,
+      lastValidBlockHeight: latestBlockhash.lastValidBlockHeight,
+    });
+
+    const secondLenderUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      secondLender.publicKey
+    ).then(acc => acc.address);
+    await mintTo(provider.connection, adminPayer, usdcMint, secondLenderUsdcAccount, admin, 500_000);
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 500_000);
+
+    await program.methods
+      .createLoanRequest(syndicatedLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: syndicatedLoanRequestPDA,
+        collateralVault: syndicatedCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const borrowerUsdcBefore = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+
+    // Each lender puts up half of the loan
+    for (const [trancheLender, trancheLenderUsdcAccount] of [
+      [lender, lenderUsdcATA],
+      [secondLender, secondLenderUsdcAccount],
+    ] as [Keypair, PublicKey][]) {
+      await program.methods
+        .fundTranche(syndicatedLoanId, new BN(500_000))
+        .accountsPartial({
+          lender: trancheLender.publicKey,
+          config: configPDA,
+          loanRequest: syndicatedLoanRequestPDA,
+          borrower: borrower.publicKey,
+          lenderPosition: PublicKey.findProgramAddressSync(
+            [Buffer.from("lender_position"), syndicatedLoanRequestPDA.toBuffer(), trancheLender.publicKey.toBuffer()],
+            program.programId
+          )[0],
+          trancheEscrow: trancheEscrowPDA,
+          lenderUsdcAccount: trancheLenderUsdcAccount,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([trancheLender])
+        .rpc();
+    }
+
+    // Fully subscribed: the borrower got the whole loan and the escrow is closed
+    let loanRequestAccount = await program.account.loanRequestState.fetch(syndicatedLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ funded: {} });
+    expect(loanRequestAccount.trancheCount).to.equal(2);
+    expect(loanRequestAccount.subscribedAmount.toNumber()).to.equal(1_000_000);
+    const borrowerUsdcAfter = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+    expect(Number(borrowerUsdcAfter.value.amount) - Number(borrowerUsdcBefore.value.amount)).to.equal(1_000_000);
+    expect(await provider.connection.getAccountInfo(trancheEscrowPDA)).to.equal(null);
+
+    // A single lender can't take over a syndicated loan
+    try {
+      await program.methods
+        .fundLoan(syndicatedLoanId)
+        .accountsPartial({
+          lender: lender.publicKey,
+          config: configPDA,
+          loanRequest: syndicatedLoanRequestPDA,
+          positionMint: positionMintOf(syndicatedLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(syndicatedLoanRequestPDA, lender.publicKey),
+          borrower: borrower.publicKey,
+          lenderUsdcAccount: lenderUsdcATA,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Funding a syndicated loan did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("AlreadyFunded");
+    }
+
+    // No single tranche lender can accept an extension or forgive the loan
+    try {
+      await program.methods
+        .acceptLoanExtension(syndicatedLoanId, new BN(60), new BN(0))
+        .accountsPartial({
+          lender: lender.publicKey,
+          loanRequest: syndicatedLoanRequestPDA,
+          positionTokenAccount: null, // no position NFT
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Extending a syndicated loan did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("SyndicatedLoanNotSupported");
+    }
+    try {
+      await program.methods
+        .forgiveLoan(syndicatedLoanId)
+        .accountsPartial({
+          lender: lender.publicKey,
+          borrower: borrower.publicKey,
+          loanRequest: syndicatedLoanRequestPDA,
+          positionTokenAccount: null, // no position NFT
+          collateralVault: syndicatedCollateralVaultPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          lenderUsdcAccount: null, // no forgiveness fee configured
+          protocolVaultUsdc: null,
+          collateralTokenVault: null, // SOL-backed loan
+          borrowerCollateralAccount: null,
+          tokenProgram: null,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Forgiving a syndicated loan did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("SyndicatedLoanNotSupported");
+    }
+
+    // Fees on top of the principal
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 50_000);
+
+    const lenderUsdcBefore = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    const secondLenderUsdcBefore = await provider.connection.getTokenAccountBalance(secondLenderUsdcAccount);
+
+    // Repay principal plus 4% lender fee and 1% protocol fee, the tranche lenders come as remaining accounts
+    await program.methods
+      .repayLoan(syndicatedLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount: null, // paid to the tranche lenders
+        loanRequest: syndicatedLoanRequestPDA,
+        positionTokenAccount: null, // no position NFT
+        collateralVault: syndicatedCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .remainingAccounts([
+        { pubkey: lenderPositionPDA, isWritable: true, isSigner: false },
+        { pubkey: lenderUsdcATA, isWritable: true, isSigner: false },
+        { pubkey: secondLenderPositionPDA, isWritable: true, isSigner: false },
+        { pubkey: secondLenderUsdcAccount, isWritable: true, isSigner: false },
+      ])
+      .signers([borrower])
+      .rpc();
+
+    loanRequestAccount = await program.account.loanRequestState.fetch(syndicatedLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ repaid: {} });
+
+    const lenderUsdcAfter = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    const secondLenderUsdcAfter = await provider.connection.getTokenAccountBalance(secondLenderUsdcAccount);
+    expect(Number(lenderUsdcAfter.value.amount) - Number(lenderUsdcBefore.value.amount)).to.equal(520_000);
+    expect(Number(secondLenderUsdcAfter.value.amount) - Number(secondLenderUsdcBefore.value.amount)).to.equal(520_000);
+
+    // Settled positions can be closed for their rent
+    const position = await program.account.lenderPositionState.fetch(secondLenderPositionPDA);
+    expect(position.settled).to.equal(true);
+    await program.methods
+      .closeLenderPosition()
+      .accountsPartial({
+        lender: secondLender.publicKey,
+        lenderPosition: secondLenderPositionPDA,
+      })
+      .signers([secondLender])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(secondLenderPositionPDA)).to.equal(null);
+  });
+
+  // Test 9h: Lender-side loan offer
+  it("Should let a borrower take a standing loan offer in one transaction", async() => {
+    const offerId = new BN(1);
+    const offerLoanId = new BN(15);
+    const [loanOfferPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_offer"), lender.publicKey.toBuffer(), offerId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [offerEscrowPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer_escrow"), loanOfferPDA.toBuffer()],
+      program.programId
+    );
+    const [offerLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), offerLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [offerCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), offerLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    // The lender escrows 2 USDC, lending at most 1 USDC for 30 days at 200% collateral
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 2_000_000);
+    await program.methods
+      .createLoanOffer(offerId, new BN(2_000_000), new BN(1_000_000), new BN(30), 20_000, new BN(0))
+      .accountsPartial({
+        lender: lender.publicKey,
+        loanOffer: loanOfferPDA,
+        offerEscrow: offerEscrowPDA,
+        lenderUsdcAccount: lenderUsdcATA,
+        offerRegistry: offerRegistryPDA,
+        offerRegistryPage: offerRegistryPagePDA,
+        config: configPDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const offerRegistryPage = await program.account.offerRegistryPageState.fetch(offerRegistryPagePDA);
+    expect(offerRegistryPage.loanOffers.map((key) => key.toBase58())).to.include(loanOfferPDA.toBase58());
+
+    const offerPositionMint = positionMintOf(offerLoanRequestPDA);
+    const lenderOfferPositionAccount = positionTokenAccountOf(offerLoanRequestPDA, lender.publicKey);
+    const acceptAccounts = {
+      borrower: borrower.publicKey,
+      loanOffer: loanOfferPDA,
+      lender: lender.publicKey,
+      offerEscrow: offerEscrowPDA,
+      loanRequest: offerLoanRequestPDA,
+      collateralVault: offerCollateralVaultPDA,
+      borrowerUsdcAccount: borrowerUsdcATA,
+      positionMint: offerPositionMint,
+      lenderPositionTokenAccount: lenderOfferPositionAccount,
+      loanRegistry: loanRegistryPDA,
+      loanRegistryPage: loanRegistryPagePDA,
+      config: configPDA,
+      priceUpdate: solPriceUpdate,
+      usdcMint,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      systemProgram: SYSTEM_PROGRAM_ID
+    };
+
+    // 150% collateral is below the lender's 200%
+    try {
+      await program.methods
+        .acceptLoanOffer(offerId, offerLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0))
+        .accountsPartial(acceptAccounts)
+        .signers([borrower])
+        .rpc();
+      assert.fail("Taking an offer with too little collateral did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InsuffientCollateral");
+    }
+
+    const borrowerUsdcBefore = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+
+    // 1 USDC at $200/SOL and 200% = 0.01 SOL
+    await program.methods
+      .acceptLoanOffer(offerId, offerLoanId, new BN(1_000_000), new BN(10_000_000), new BN(30), new BN(0))
+      .accountsPartial(acceptAccounts)
+      .signers([borrower])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(offerLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ funded: {} });
+    expect(loanRequestAccount.lender.toBase58()).to.equal(lender.publicKey.toBase58());
+    expect(loanRequestAccount.terms.collateralRatioBps).to.equal(20_000);
+    const borrowerUsdcAfter = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+    expect(Number(borrowerUsdcAfter.value.amount) - Number(borrowerUsdcBefore.value.amount)).to.equal(1_000_000);
+    const loanOffer = await program.account.loanOfferState.fetch(loanOfferPDA);
+    expect(loanOffer.availableAmount.toNumber()).to.equal(1_000_000);
+
+    // The offer's lender holds the position NFT of the loan, as with fund_loan
+    expect(loanRequestAccount.positionMint.toBase58()).to.equal(offerPositionMint.toBase58());
+    const lenderOfferPosition = await provider.connection.getTokenAccountBalance(lenderOfferPositionAccount);
+    expect(lenderOfferPosition.value.amount).to.equal("1");
+
+    // The loan settles like any other, principal plus fees go to the offer's lender
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 50_000);
+    await program.methods
+      .repayLoan(offerLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount: lenderUsdcATA,
+        loanRequest: offerLoanRequestPDA,
+        positionTokenAccount: lenderOfferPositionAccount,
+        collateralVault: offerCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .signers([borrower])
+      .rpc();
+
+    // Withdrawing the offer returns the unused USDC to the lender
+    const lenderUsdcBefore = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    await program.methods
+      .cancelLoanOffer(offerId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        loanOffer: loanOfferPDA,
+        offerEscrow: offerEscrowPDA,
+        lenderUsdcAccount: lenderUsdcATA,
+        offerRegistry: offerRegistryPDA,
+        offerRegistryPage: offerRegistryPagePDA,
+        tokenProgram: TOKEN_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const lenderUsdcAfter = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    expect(Number(lenderUsdcAfter.value.amount) - Number(lenderUsdcBefore.value.amount)).to.equal(1_000_000);
+    expect(await provider.connection.getAccountInfo(loanOfferPDA)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(offerEscrowPDA)).to.equal(null);
+  });
+
+  // Test 9i: Pooled lending vault
+  it("Should fund an eligible request from the lending pool and share the repayment with depositors", async() => {
+    const poolLoanId = new BN(16);
+    const [poolLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), poolLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [poolCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [poolLoanPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [poolLoanUsdcPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan_usdc"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    // Loans of 0.1 to 5 USDC for 30 days at 200% collateral, at most 80% of the pool lent out
+    await program.methods
+      .createLendingPool({
+        minLoanAmount: new BN(100_000),
+        maxLoanAmount: new BN(5_000_000),
+        maxDurationDays: new BN(30),
+        minCollateralRatioBps: 20_000,
+        maxUtilizationBps: 8_000
+      })
+      .accountsPartial({
+        admin,
+        config: configPDA,
+        lendingPool: lendingPoolPDA,
+        shareMint: shareMintPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([adminPayer])
+      .rpc();
+
+    // The lender deposits 2 USDC, the first deposit mints shares one for one
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 2_000_000);
+    const lenderShareATA = anchor.utils.token.associatedAddress({ mint: shareMintPDA, owner: lender.publicKey });
+    await program.methods
+      .depositToPool(new BN(2_000_000))
+      .accountsPartial({
+        depositor: lender.publicKey,
+        lendingPool: lendingPoolPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        shareMint: shareMintPDA,
+        depositorUsdcAccount: lenderUsdcATA,
+        depositorShareAccount: lenderShareATA,
+        priceUpdate: solPriceUpdate,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const lenderShares = await provider.connection.getTokenAccountBalance(lenderShareATA);
+    expect(lenderShares.value.amount).to.equal("2000000");
+
+    // 1 USDC at $200/SOL and 200% = 0.01 SOL
+    await program.methods
+      .createLoanRequest(
+        poolLoanId,
+        new BN(1_000_000),
+        new BN(10_000_000),
+        new BN(30),
+        new BN(0), // registry page index
+        null, // flat lender fee, no APR
+        null // open to any lender
+      )
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: poolLoanRequestPDA,
+        collateralVault: poolCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    // Anyone can ask the pool to fund the request, here the lender acts as keeper
+    const borrowerUsdcBefore = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+    await program.methods
+      .fundFromPool(poolLoanId)
+      .accountsPartial({
+        keeper: lender.publicKey,
+        config: configPDA,
+        lendingPool: lendingPoolPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        loanRequest: poolLoanRequestPDA,
+        poolLoan: poolLoanPDA,
+        poolLoanUsdc: poolLoanUsdcPDA,
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        priceUpdate: solPriceUpdate,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(poolLoanRequestPDA);
+    expect(loanRequestAccount.status).to.deep.equal({ funded: {} });
+    expect(loanRequestAccount.lender.toBase58()).to.equal(poolLoanPDA.toBase58());
+    const borrowerUsdcAfter = await provider.connection.getTokenAccountBalance(borrowerUsdcATA);
+    expect(Number(borrowerUsdcAfter.value.amount) - Number(borrowerUsdcBefore.value.amount)).to.equal(1_000_000);
+    let lendingPool = await program.account.lendingPoolState.fetch(lendingPoolPDA);
+    expect(lendingPool.lentPrincipal.toNumber()).to.equal(1_000_000);
+
+    const redeemAccounts = {
+      owner: lender.publicKey,
+      lendingPool: lendingPoolPDA,
+      poolUsdcVault: poolUsdcVaultPDA,
+      shareMint: shareMintPDA,
+      ownerShareAccount: lenderShareATA,
+      ownerUsdcAccount: lenderUsdcATA,
+      priceUpdate: solPriceUpdate,
+      tokenProgram: TOKEN_PROGRAM_ID
+    };
+
+    // Half of the pool is lent out, all the shares can't be redeemed yet
+    try {
+      await program.methods
+        .redeemFromPool(new BN(2_000_000))
+        .accountsPartial(redeemAccounts)
+        .signers([lender])
+        .rpc();
+      assert.fail("Redeeming more than the pool liquidity did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InsufficientPoolLiquidity");
+    }
+
+    // The borrower repays into the pool loan's USDC account, the recorded lender
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 50_000);
+    const repayPoolLoan = (lenderUsdcAccount: PublicKey, lendingPool: PublicKey | null = lendingPoolPDA) => program.methods
+      .repayLoan(poolLoanId)
+      .accountsPartial({
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount,
+        loanRequest: poolLoanRequestPDA,
+        positionTokenAccount: null, // no position NFT
+        collateralVault: poolCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        lendingPool,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .signers([borrower])
+      .rpc();
+
+    // Another USDC account owned by the pool loan PDA is never settled into the pool
+    const strayPoolLoanUsdcAccount = await createAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      poolLoanPDA,
+      Keypair.generate()
+    );
+    try {
+      await repayPoolLoan(strayPoolLoanUsdcAccount);
+      assert.fail("Repaying a pool loan outside its USDC account did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotRightLender");
+    }
+
+    // The lending pool has to be passed to record the lender fee paid to the pool loan
+    try {
+      await repayPoolLoan(poolLoanUsdcPDA, null);
+      assert.fail("Repaying a pool loan without the lending pool did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("MissingLendingPool");
+    }
+    await repayPoolLoan(poolLoanUsdcPDA);
+
+    lendingPool = await program.account.lendingPoolState.fetch(lendingPoolPDA);
+    expect(lendingPool.lentPrincipal.toNumber()).to.equal(1_000_000);
+    expect(lendingPool.unsettledEarnings.toNumber()).to.equal(40_000);
+
+    // The repayment counts in the pool value before it is settled:
+    // a quarter of the shares is worth (2.04 + 1 virtual USDC) * 0.5 / (2 + 1 virtual shares)
+    const redeemQuarter = async () => {
+      const before = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+      await program.methods
+        .redeemFromPool(new BN(500_000))
+        .accountsPartial(redeemAccounts)
+        .signers([lender])
+        .rpc();
+      const after = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+      return Number(after.value.amount) - Number(before.value.amount);
+    };
+    expect(await redeemQuarter()).to.equal(506_666);
+
+    // Settling moves principal plus the 4% lender fee into the pool vault
+    await program.methods
+      .settlePoolLoan(poolLoanId)
+      .accountsPartial({
+        keeper: lender.publicKey,
+        loanRequest: poolLoanRequestPDA,
+        lendingPool: lendingPoolPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        poolLoan: poolLoanPDA,
+        poolLoanUsdc: poolLoanUsdcPDA,
+        rentReceiver: lender.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    lendingPool = await program.account.lendingPoolState.fetch(lendingPoolPDA);
+    expect(lendingPool.lentPrincipal.toNumber()).to.equal(0);
+    expect(lendingPool.unsettledEarnings.toNumber()).to.equal(0);
+    const poolVault = await provider.connection.getTokenAccountBalance(poolUsdcVaultPDA);
+    expect(poolVault.value.amount).to.equal(String(2_040_000 - 506_666));
+    expect(await provider.connection.getAccountInfo(poolLoanPDA)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(poolLoanUsdcPDA)).to.equal(null);
+
+    // Settling didn't change the pool value, the next quarter is worth the same
+    expect(await redeemQuarter()).to.equal(506_666);
+
+    // A request below the pool's minimum loan amount is refused, so dust loans can't tie up the pool
+    const dustLoanId = new BN(21);
+    const [dustLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), dustLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [dustCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), dustLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [dustPoolLoanPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan"), dustLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [dustPoolLoanUsdcPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan_usdc"), dustLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createLoanRequest(dustLoanId, new BN(50_000), new BN(1_000_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: dustLoanRequestPDA,
+        collateralVault: dustCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+    try {
+      await program.methods
+        .fundFromPool(dustLoanId)
+        .accountsPartial({
+          keeper: lender.publicKey,
+          config: configPDA,
+          lendingPool: lendingPoolPDA,
+          poolUsdcVault: poolUsdcVaultPDA,
+          loanRequest: dustLoanRequestPDA,
+          poolLoan: dustPoolLoanPDA,
+          poolLoanUsdc: dustPoolLoanUsdcPDA,
+          borrower: borrower.publicKey,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          priceUpdate: solPriceUpdate,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Funding a loan below the pool minimum did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("LoanOutsidePoolPolicy");
+    }
+    await program.methods
+      .cancelLoanRequest(dustLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: dustLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        trancheEscrow: null, // no tranches to refund
+      })
+      .signers([borrower])
+      .rpc();
+
+    // A deposit too small to mint a share is refused
+    try {
+      await program.methods
+        .depositToPool(new BN(1))
+        .accountsPartial({
+          depositor: lender.publicKey,
+          lendingPool: lendingPoolPDA,
+          poolUsdcVault: poolUsdcVaultPDA,
+          shareMint: shareMintPDA,
+          depositorUsdcAccount: lenderUsdcATA,
+          depositorShareAccount: lenderShareATA,
+          priceUpdate: solPriceUpdate,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Depositing for no shares did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("PoolAmountTooSmall");
+    }
+  });
+
+  // Test 9j: Transferable lender position
+  it("Should pay the holder of the position NFT once the lender sells it", async() => {
+    const positionLoanId = new BN(17);
+    const buyer = Keypair.generate();
+    const [positionLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), positionLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [positionCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), positionLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const positionMint = positionMintOf(positionLoanRequestPDA);
+    const lenderPositionAccount = positionTokenAccountOf(positionLoanRequestPDA, lender.publicKey);
+
+    await program.methods
+      .createLoanRequest(positionLoanId, new BN(1_000_000), new BN(7_500_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: positionLoanRequestPDA,
+        collateralVault: positionCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 1_000_000);
+    const fund = (recipient: PublicKey) => program.methods
+      .fundLoan(positionLoanId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        config: configPDA,
+        loanRequest: positionLoanRequestPDA,
+        positionMint,
+        lenderPositionTokenAccount: lenderPositionAccount,
+        borrower: recipient,
+        lenderUsdcAccount: lenderUsdcATA,
+        borrowerUsdcAccount: anchor.utils.token.associatedAddress({ mint: usdcMint, owner: recipient }),
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    // The principal can only go to the borrower recorded on the loan
+    try {
+      await fund(buyer.publicKey);
+      assert.fail("Funding another account than the borrower did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotRightBorrower");
+    }
+    await fund(borrower.publicKey);
+
+    // The lender holds the only position NFT of the loan
+    const positionSupply = await provider.connection.getTokenSupply(positionMint);
+    expect(positionSupply.value.amount).to.equal("1");
+    const loanRequestAccount = await program.account.loanRequestState.fetch(positionLoanRequestPDA);
+    expect(loanRequestAccount.positionMint.toBase58()).to.equal(positionMint.toBase58());
+    expect(loanRequestAccount.positionCount).to.equal(1);
+
+    // The lender sells the position to the buyer
+    const buyerPositionAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      positionMint,
+      buyer.publicKey
+    ).then(acc => acc.address);
+    const buyerUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      buyer.publicKey
+    ).then(acc => acc.address);
+    await transfer(provider.connection, adminPayer, lenderPositionAccount, buyerPositionAccount, lender, 1);
+
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 50_000);
+    const repay = (lenderUsdcAccount: PublicKey, positionTokenAccount: PublicKey) => program.methods
+      .repayLoan(positionLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount,
+        positionTokenAccount,
+        loanRequest: positionLoanRequestPDA,
+        collateralVault: positionCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .signers([borrower])
+      .rpc();
+
+    // The original lender is no longer owed the loan
+    try {
+      await repay(lenderUsdcATA, lenderPositionAccount);
+      assert.fail("Repaying the lender who sold the position did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotRightLender");
+    }
+
+    // Principal plus the 4% lender fee go to the buyer
+    await repay(buyerUsdcAccount, buyerPositionAccount);
+    const buyerUsdc = await provider.connection.getTokenAccountBalance(buyerUsdcAccount);
+    expect(buyerUsdc.value.amount).to.equal("1040000");
+  });
+
+  // Test 9k: Refinance
+  it("Should let a new lender pay off the current lender and take over the loan", async() => {
+    const refinanceLoanId = new BN(18);
+    const newLender = Keypair.generate();
+    const [refinanceLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), refinanceLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [refinanceCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), refinanceLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const positionMint = positionMintOf(refinanceLoanRequestPDA);
+    const lenderPositionAccount = positionTokenAccountOf(refinanceLoanRequestPDA, lender.publicKey);
+
+    // Extra collateral so that the payoff is still covered as the new principal
+    await program.methods
+      .createLoanRequest(refinanceLoanId, new BN(1_000_000), new BN(9_000_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: refinanceLoanRequestPDA,
+        collateralVault: refinanceCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 1_000_000);
+    await program.methods
+      .fundLoan(refinanceLoanId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        config: configPDA,
+        loanRequest: refinanceLoanRequestPDA,
+        positionMint,
+        lenderPositionTokenAccount: lenderPositionAccount,
+        borrower: borrower.publicKey,
+        lenderUsdcAccount: lenderUsdcATA,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const airdropSig = await provider.connection.requestAirdrop(newLender.publicKey, LAMPORTS_PER_SOL);
+    await provider.connection.confirmTransaction(airdropSig);
+    const newPositionMint = positionMintOf(refinanceLoanRequestPDA, 1);
+    const newLenderPositionAccount = positionTokenAccountOf(refinanceLoanRequestPDA, newLender.publicKey, 1);
+    const newLenderUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      newLender.publicKey
+    ).then(acc => acc.address);
+    await mintTo(provider.connection, adminPayer, usdcMint, newLenderUsdcAccount, admin, 1_050_000);
+
+    const lenderUsdcBefore = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    const vaultLamportsBefore = await provider.connection.getBalance(refinanceCollateralVaultPDA);
+
+    const refinance = (durationDays: number) => program.methods
+      .refinanceLoan(refinanceLoanId, new BN(durationDays), 500)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        borrower: borrower.publicKey,
+        newLender: newLender.publicKey,
+        newLenderUsdcAccount,
+        lenderUsdcAccount: lenderUsdcATA,
+        positionTokenAccount: lenderPositionAccount,
+        loanRequest: refinanceLoanRequestPDA,
+        positionMint: newPositionMint,
+        newLenderPositionTokenAccount: newLenderPositionAccount,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        collateralMintConfig: null, // SOL-backed loan
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower, newLender])
+      .rpc();
+
+    // A refinanced loan needs a duration
+    try {
+      await refinance(0);
+      assert.fail("Refinancing for 0 days did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidDuration");
+    }
+
+    // The new lender pays 1 USDC + 4% to the lender and 1% to the protocol, for 60 days at 5% APR
+    await refinance(60);
+
+    const lenderUsdcAfter = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    expect(Number(lenderUsdcAfter.value.amount) - Number(lenderUsdcBefore.value.amount)).to.equal(1_040_000);
+    const newLenderUsdc = await provider.connection.getTokenAccountBalance(newLenderUsdcAccount);
+    expect(newLenderUsdc.value.amount).to.equal("0");
+
+    // The loan now belongs to the new lender and the collateral never moved
+    const loanRequestAccount = await program.account.loanRequestState.fetch(refinanceLoanRequestPDA);
+    expect(loanRequestAccount.lender.toBase58()).to.equal(newLender.publicKey.toBase58());
+    expect(loanRequestAccount.outstandingPrincipal.toNumber()).to.equal(1_050_000);
+    expect(loanRequestAccount.durationDays.toNumber()).to.equal(60);
+    expect(loanRequestAccount.aprBps).to.equal(500);
+    expect(loanRequestAccount.positionMint.toBase58()).to.equal(newPositionMint.toBase58());
+    expect(loanRequestAccount.positionCount).to.equal(2);
+    const newLenderPosition = await provider.connection.getTokenAccountBalance(newLenderPositionAccount);
+    expect(newLenderPosition.value.amount).to.equal("1");
+    const vaultLamportsAfter = await provider.connection.getBalance(refinanceCollateralVaultPDA);
+    expect(vaultLamportsAfter).to.equal(vaultLamportsBefore);
+
+    // The borrower repays the new lender, which frees the registry slot
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 1_102_500);
+    const repay = (lenderUsdcAccount: PublicKey, positionTokenAccount: PublicKey) => program.methods
+      .repayLoan(refinanceLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount,
+        positionTokenAccount,
+        loanRequest: refinanceLoanRequestPDA,
+        collateralVault: refinanceCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .signers([borrower])
+      .rpc();
+
+    // The position NFT of the previous lender is void
+    try {
+      await repay(lenderUsdcATA, lenderPositionAccount);
+      assert.fail("Repaying the previous position holder did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotRightLender");
+    }
+    await repay(newLenderUsdcAccount, newLenderPositionAccount);
+    const newLenderRepaid = await provider.connection.getTokenAccountBalance(newLenderUsdcAccount);
+    expect(Number(newLenderRepaid.value.amount)).to.be.at.least(1_050_000);
+  });
+
+  // Test 9l: Pool loans follow the pool policy
+  it("Should hold a pool loan to the pool collateral ratio and liquidate it into the pool", async() => {
+    const poolLoanId = new BN(19);
+    const [poolLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), poolLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [poolCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [poolLoanPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [poolLoanUsdcPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_loan_usdc"), poolLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    // 0.5 USDC at $200/SOL and the pool's 200% = 0.005 SOL
+    await program.methods
+      .createLoanRequest(poolLoanId, new BN(500_000), new BN(5_000_000), new BN(30), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: poolLoanRequestPDA,
+        collateralVault: poolCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    await program.methods
+      .fundFromPool(poolLoanId)
+      .accountsPartial({
+        keeper: lender.publicKey,
+        config: configPDA,
+        lendingPool: lendingPoolPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        loanRequest: poolLoanRequestPDA,
+        poolLoan: poolLoanPDA,
+        poolLoanUsdc: poolLoanUsdcPDA,
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        priceUpdate: solPriceUpdate,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(poolLoanRequestPDA);
+    expect(loanRequestAccount.terms.collateralRatioBps).to.equal(20_000);
+
+    // 0.004 SOL left would still be 160%, above the protocol ratio but below the pool's
+    try {
+      await program.methods
+        .withdrawExcessCollateral(poolLoanId, new BN(1_000_000))
+        .accountsPartial({
+          borrower: borrower.publicKey,
+          loanRequest: poolLoanRequestPDA,
+          collateralVault: poolCollateralVaultPDA,
+          priceUpdate: solPriceUpdate,
+          collateralMintConfig: null, // SOL-backed loan
+          collateralTokenVault: null,
+          borrowerCollateralAccount: null,
+          tokenProgram: null,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+      assert.fail("Withdrawing below the pool collateral ratio did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InsuffientCollateral");
+    }
+
+    // Any keeper can liquidate the pool loan, the pool loan PDA can't sign for it
+    const liquidate = (priceUpdate: PublicKey) => program.methods
+      .liquidatePoolLoan(poolLoanId)
+      .accountsPartial({
+        keeper: borrower.publicKey,
+        borrower: borrower.publicKey,
+        loanRequest: poolLoanRequestPDA,
+        collateralVault: poolCollateralVaultPDA,
+        lendingPool: lendingPoolPDA,
+        poolUsdcVault: poolUsdcVaultPDA,
+        poolLoan: poolLoanPDA,
+        poolLoanUsdc: poolLoanUsdcPDA,
+        rentReceiver: lender.publicKey,
+        protocolVault: protocolVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        priceUpdate,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    // 0.005 SOL at $200 is worth $1, well above 110% of 0.5 USDC
+    try {
+      await liquidate(solPriceUpdate);
+      assert.fail("Liquidating a healthy pool loan did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("CannotLiquidateYet");
+    }
+
+    // At $100 it is worth $0.5, the seized SOL goes to the pool and counts in its value
+    const poolBefore = await program.account.lendingPoolState.fetch(lendingPoolPDA);
+    const poolLamportsBefore = await provider.connection.getBalance(lendingPoolPDA);
+    await liquidate(await postSolPrice(100_000_000));
+
+    const poolAfter = await program.account.lendingPoolState.fetch(lendingPoolPDA);
+    const poolLamportsAfter = await provider.connection.getBalance(lendingPoolPDA);
+    const seized = poolAfter.seizedCollateral.toNumber() - poolBefore.seizedCollateral.toNumber();
+    expect(seized).to.be.greaterThan(0);
+    expect(poolLamportsAfter - poolLamportsBefore).to.equal(seized);
+    expect(poolBefore.lentPrincipal.toNumber() - poolAfter.lentPrincipal.toNumber()).to.equal(500_000);
+    expect(poolAfter.unsettledEarnings.toNumber()).to.equal(poolBefore.unsettledEarnings.toNumber());
+
+    const liquidatedLoan = await program.account.loanRequestState.fetch(poolLoanRequestPDA);
+    expect(liquidatedLoan.status).to.deep.equal({ liquidated: {} });
+    expect(await provider.connection.getAccountInfo(poolLoanPDA)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(poolLoanUsdcPDA)).to.equal(null);
+    expect(await provider.connection.getAccountInfo(poolCollateralVaultPDA)).to.equal(null);
+  });
+
+  // Test 10: Claim Collateral After Deadline
+  it("Should claim collateral after loan deadline", async() => {
+    // Create and fund a loan that will expire
+    const expiredLoanId = new BN(20);
+    const loanAmount = new BN(1_000_000); // 1 USDC
+    const collateral = new BN(7_500_000); // 0.0075 SOL
+    const noOfDays = new BN(30);
+    const SOL_PRICE = 200_000_000; // $200 with 6 decimals
+    
+    // Use a different loan ID for this test
+    
+    const [expiredLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), expiredLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    
+    const [expiredCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), expiredLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    try {
+      // Create loan request
+      await program.methods
+        .createLoanRequest(
+          expiredLoanId,
+          loanAmount,
+          collateral,
+          noOfDays,
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
+        )
+        .accountsPartial({
+          borrower: borrower.publicKey,
+          loanRequest: expiredLoanRequestPDA,
+          collateralVault: expiredCollateralVaultPDA,
+          loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+
+      // Fund the loan
+      await program.methods
+        .fundLoan(expiredLoanId)
+        .accountsPartial({
+          lender: lender.publicKey,
+          config: configPDA,
+          loanRequest: expiredLoanRequestPDA,
+          positionMint: positionMintOf(expiredLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(expiredLoanRequestPDA, lender.publicKey),
+          borrower: borrower.publicKey,
+          lenderUsdcAccount: lenderUsdcATA,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+
+      // NOTE: In a real test environment, we would need to manipulate time
+      // Since we can't do that in this environment, we'll just note that
+      // this test would normally fail with LoanIsNotExpired error
+      
+      // For demonstration purposes, we'll try to claim collateral (expecting it to fail)
+      try {
+        await program.methods
+          .claimCollateral(expiredLoanId)
+          .accountsPartial({
+            collateralTokenVault: null, // SOL-backed loan
+            lender: lender.publicKey,
+            borrower: borrower.publicKey,
+            loanRequest: expiredLoanRequestPDA,
+            positionTokenAccount: positionTokenAccountOf(expiredLoanRequestPDA, lender.publicKey),
+            collateralVault: expiredCollateralVaultPDA,
+            protocolVault: protocolVaultPDA,
+            loanRegistry: loanRegistryPDA,
+            loanRegistryPage: loanRegistryPagePDA,
+            priceUpdate: solPriceUpdate,
+            systemProgram: SYSTEM_PROGRAM_ID
+          })
+          .signers([lender])
+          .rpc();
+        
+        // If we reach here, the test should fail
+        assert.fail("Should not be able to claim collateral before deadline");
+      } catch (error) {
+        // Expected error since we can't manipulate time in this test environment
+        console.log("Expected error:", error.toString());
+      }
+    } catch (error) {
+      console.error("Error in claim collateral test:", error);
+      throw error;
+    }
+  });
+
+  // Test 10b: Grace window
+  it("Should reject a claim during the grace window and charge the late penalty on repayment", async() => {
+    const graceLoanId = new BN(33);
+    const [graceLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), graceLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [graceCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), graceLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const lenderPositionAccount = positionTokenAccountOf(graceLoanRequestPDA, lender.publicKey);
+
+    // A loan due as soon as it is funded, with a one day grace period
+    await updateTerms({ ...defaultTerms, gracePeriodDays: 1 });
+    await program.methods
+      .createLoanRequest(graceLoanId, new BN(1_000_000), new BN(7_500_000), new BN(0), new BN(0), null, null)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: graceLoanRequestPDA,
+        collateralVault: graceCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+    await updateTerms(defaultTerms);
+
+    await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 1_000_000);
+    await program.methods
+      .fundLoan(graceLoanId)
+      .accountsPartial({
+        lender: lender.publicKey,
+        config: configPDA,
+        loanRequest: graceLoanRequestPDA,
+        positionMint: positionMintOf(graceLoanRequestPDA),
+        lenderPositionTokenAccount: lenderPositionAccount,
+        borrower: borrower.publicKey,
+        lenderUsdcAccount: lenderUsdcATA,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc();
+    await sleep(2_000);
+
+    // Past the deadline but inside the grace window, the collateral can't be claimed yet
+    try {
+      await program.methods
+        .claimCollateral(graceLoanId)
+        .accountsPartial({
+          collateralTokenVault: null, // SOL-backed loan
+          lender: lender.publicKey,
+          borrower: borrower.publicKey,
+          loanRequest: graceLoanRequestPDA,
+          positionTokenAccount: lenderPositionAccount,
+          collateralVault: graceCollateralVaultPDA,
+          protocolVault: protocolVaultPDA,
+          loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          priceUpdate: solPriceUpdate,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Claiming collateral during the grace window did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("LoanIsNotExpired");
+    }
+
+    // Repaying a few seconds late starts the first day: 0.5% of the principal goes to the lender on top of the 4% fee
+    await mintTo(provider.connection, adminPayer, usdcMint, borrowerUsdcATA, admin, 55_000);
+    const lenderUsdcBefore = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    await program.methods
+      .repayLoan(graceLoanId)
+      .accountsPartial({
+        lendingPool: null, // not a pool loan
+        collateralTokenVault: null, // SOL-backed loan
+        borrower: borrower.publicKey,
+        borrowerUsdcAccount: borrowerUsdcATA,
+        lenderUsdcAccount: lenderUsdcATA,
+        positionTokenAccount: lenderPositionAccount,
+        loanRequest: graceLoanRequestPDA,
+        collateralVault: graceCollateralVaultPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        config: configPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        usdcMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY
+      })
+      .signers([borrower])
+      .rpc();
+    const lenderUsdcAfter = await provider.connection.getTokenAccountBalance(lenderUsdcATA);
+    expect(Number(lenderUsdcAfter.value.amount) - Number(lenderUsdcBefore.value.amount)).to.equal(1_045_000);
+  });
+
+  // Test 11: Liquidate Loan
+  it("Should liquidate a loan when collateral value drops", async() => {
+    // Create and fund a loan
+    const loanAmount = new BN(1_000_000); // 1 USDC
+    const collateral = new BN(7_500_000); // 0.0075 SOL
+    const noOfDays = new BN(30);
+    const SOL_PRICE = 200_000_000; // $200 with 6 decimals
+    
+    // Use a different loan ID for this test
+    const liquidationLoanId = new BN(30);
+    
+    const [liquidationLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), liquidationLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    
+    const [liquidationCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), liquidationLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    try {
+      // Create loan request
+      await program.methods
+        .createLoanRequest(
+          liquidationLoanId,
+          loanAmount,
+          collateral,
+          noOfDays,
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
+        )
+        .accountsPartial({
+          borrower: borrower.publicKey,
+          loanRequest: liquidationLoanRequestPDA,
+          collateralVault: liquidationCollateralVaultPDA,
+          loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+
+      // Fund the loan
+      await program.methods
+        .fundLoan(liquidationLoanId)
+        .accountsPartial({
+          lender: lender.publicKey,
+          config: configPDA,
+          loanRequest: liquidationLoanRequestPDA,
+          positionMint: positionMintOf(liquidationLoanRequestPDA),
+          lenderPositionTokenAccount: positionTokenAccountOf(liquidationLoanRequestPDA, lender.publicKey),
+          borrower: borrower.publicKey,
+          lenderUsdcAccount: lenderUsdcATA,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+
+      // Now we'll simulate a price drop by posting a lower SOL price to the oracle
+      // In real markets, SOL price would drop from $200 to $100
+      const loweredSolPrice = 100_000_000; // $100 with 6 decimals
+      const loweredPriceUpdate = await postSolPrice(loweredSolPrice);
+      
+      // Try to liquidate the loan with the new lower price
       try {
         await program.methods
-          .liquidateLoan(liquidationLoanId
-            ,new BN(loweredSolPrice))
+          .liquidateLoan(liquidationLoanId)
           .accountsPartial({
+            collateralTokenVault: null, // SOL-backed loan
             lender: lender.publicKey,
+            borrower: borrower.publicKey,
             loanRequest: liquidationLoanRequestPDA,
+            positionTokenAccount: positionTokenAccountOf(liquidationLoanRequestPDA, lender.publicKey),
             collateralVault: liquidationCollateralVaultPDA,
             loanRegistry: loanRegistryPDA,
+            loanRegistryPage: loanRegistryPagePDA,
             protocolVault: protocolVaultPDA,
-            systemProgram: SYSTEM_PROGRAM_ID,
-            rent: anchor.web3.SYSVAR_RENT_PUBKEY
+            priceUpdate: loweredPriceUpdate,
+            systemProgram: SYSTEM_PROGRAM_ID
           })
           .signers([lender])
           .rpc();
@@ -718,14 +2616,10 @@ describe("KINLEND PROTOCOL", () => {
         const protocolVaultBalance = await provider.connection.getBalance(protocolVaultPDA);
         expect(protocolVaultBalance).to.be.greaterThan(0);
         
-        // Try to fetch the loan request account - should fail as it's closed
-        try {
-          await program.account.loanRequestState.fetch(liquidationLoanRequestPDA);
-          assert.fail("Loan request account should be closed");
-        } catch (error) {
-          // Expected error
-          expect(error.toString()).to.include("Account does not exist");
-        }
+        // The loan request stays open with its terminal status, the collateral vault is closed
+        const liquidatedLoan = await program.account.loanRequestState.fetch(liquidationLoanRequestPDA);
+        expect(liquidatedLoan.status).to.deep.equal({ liquidated: {} });
+        expect(await provider.connection.getAccountInfo(liquidationCollateralVaultPDA)).to.equal(null);
       } catch (error) {
         // This might fail if the price drop isn't enough to trigger liquidation
         console.error("Error in liquidation test:", error);
@@ -741,6 +2635,258 @@ describe("KINLEND PROTOCOL", () => {
     }
   });
 
+  // Test 11a: Collateral vault rent is kept apart from the collateral
+  it("Should split exactly the collateral on claim and liquidation and return the vault rent to the borrower", async() => {
+    const openLoan = async (loanId: BN, collateral: number, durationDays: number) => {
+      const [loanRequest] = PublicKey.findProgramAddressSync(
+        [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), loanId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const [collateralVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("collateral_vault"), loanRequest.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .createLoanRequest(loanId, new BN(1_000_000), new BN(collateral), new BN(durationDays), new BN(0), null, null)
+        .accountsPartial({
+          borrower: borrower.publicKey,
+          loanRequest,
+          collateralVault,
+          loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+
+      await mintTo(provider.connection, adminPayer, usdcMint, lenderUsdcATA, admin, 1_000_000);
+      await program.methods
+        .fundLoan(loanId)
+        .accountsPartial({
+          lender: lender.publicKey,
+          config: configPDA,
+          loanRequest,
+          positionMint: positionMintOf(loanRequest),
+          lenderPositionTokenAccount: positionTokenAccountOf(loanRequest, lender.publicKey),
+          borrower: borrower.publicKey,
+          lenderUsdcAccount: lenderUsdcATA,
+          borrowerUsdcAccount: borrowerUsdcATA,
+          usdcMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+
+      return { loanRequest, collateralVault };
+    };
+
+    // Lamports received by the lender, the protocol vault and the borrower while `settle` runs.
+    // The fee payer is the provider wallet, so none of them pays for the transaction.
+    const settleAndMeasure = async (collateralVault: PublicKey, settle: () => Promise<string>) => {
+      const vaultInfo = await provider.connection.getAccountInfo(collateralVault);
+      const rent = await provider.connection.getMinimumBalanceForRentExemption(vaultInfo.data.length);
+      const [lenderBefore, protocolBefore, borrowerBefore] = await Promise.all([
+        provider.connection.getBalance(lender.publicKey),
+        provider.connection.getBalance(protocolVaultPDA),
+        provider.connection.getBalance(borrower.publicKey),
+      ]);
+      await settle();
+      const [lenderAfter, protocolAfter, borrowerAfter] = await Promise.all([
+        provider.connection.getBalance(lender.publicKey),
+        provider.connection.getBalance(protocolVaultPDA),
+        provider.connection.getBalance(borrower.publicKey),
+      ]);
+      expect(await provider.connection.getAccountInfo(collateralVault)).to.equal(null);
+      return {
+        vaultLamports: vaultInfo.lamports,
+        rent,
+        lender: lenderAfter - lenderBefore,
+        protocol: protocolAfter - protocolBefore,
+        borrower: borrowerAfter - borrowerBefore,
+      };
+    };
+
+    // Claim: a loan due now with no grace period defaults as soon as the clock moves on
+    await updateTerms({ ...defaultTerms, gracePeriodDays: 0 });
+    const claimed = await openLoan(new BN(31), 7_500_000, 0);
+    await updateTerms(defaultTerms);
+    await sleep(2_000);
+
+    const claim = await settleAndMeasure(claimed.collateralVault, () => program.methods
+      .claimCollateral(new BN(31))
+      .accountsPartial({
+        collateralTokenVault: null, // SOL-backed loan
+        lender: lender.publicKey,
+        borrower: borrower.publicKey,
+        loanRequest: claimed.loanRequest,
+        positionTokenAccount: positionTokenAccountOf(claimed.loanRequest, lender.publicKey),
+        collateralVault: claimed.collateralVault,
+        protocolVault: protocolVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc());
+
+    // 1.04 USDC at $200 = 0.0052 SOL to the lender, 10% of it to the protocol, the surplus and the rent to the borrower
+    expect(claim.vaultLamports).to.equal(7_500_000 + claim.rent);
+    expect(claim.lender).to.equal(5_200_000);
+    expect(claim.protocol).to.equal(520_000);
+    expect(claim.borrower).to.equal(1_780_000 + claim.rent);
+    expect(claim.lender + claim.protocol + claim.borrower - claim.rent).to.equal(7_500_000);
+
+    // Liquidation: 0.009 SOL at $120 is worth $1.08, below 110% of 1 USDC
+    const liquidated = await openLoan(new BN(32), 9_000_000, 30);
+    const loweredPriceUpdate = await postSolPrice(120_000_000);
+
+    const liquidation = await settleAndMeasure(liquidated.collateralVault, () => program.methods
+      .liquidateLoan(new BN(32))
+      .accountsPartial({
+        collateralTokenVault: null, // SOL-backed loan
+        lender: lender.publicKey,
+        borrower: borrower.publicKey,
+        loanRequest: liquidated.loanRequest,
+        positionTokenAccount: positionTokenAccountOf(liquidated.loanRequest, lender.publicKey),
+        collateralVault: liquidated.collateralVault,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        protocolVault: protocolVaultPDA,
+        priceUpdate: loweredPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([lender])
+      .rpc());
+
+    // 1.04 USDC at $120 = 8_666_667 lamports to the lender, 1.82% of it to the protocol, the rest and the rent to the borrower
+    expect(liquidation.vaultLamports).to.equal(9_000_000 + liquidation.rent);
+    expect(liquidation.lender).to.equal(8_666_667);
+    expect(liquidation.protocol).to.equal(157_733);
+    expect(liquidation.borrower).to.equal(175_600 + liquidation.rent);
+    expect(liquidation.lender + liquidation.protocol + liquidation.borrower - liquidation.rent).to.equal(9_000_000);
+  });
+
+  // Test 11b: Withdraw protocol fees
+  it("Should only let the admin withdraw SOL fees above the rent-exempt minimum", async() => {
+    const vaultBalance = await provider.connection.getBalance(protocolVaultPDA);
+    const vaultInfo = await provider.connection.getAccountInfo(protocolVaultPDA);
+    const rentExempt = await provider.connection.getMinimumBalanceForRentExemption(vaultInfo.data.length);
+    const available = vaultBalance - rentExempt;
+
+    // Withdrawing more than the surplus must fail
+    try {
+      await program.methods
+        .withdrawProtocolFees(new BN(available + 1))
+        .accountsPartial({
+          admin,
+          config: configPDA,
+          protocolVault: protocolVaultPDA,
+          treasury: admin,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([adminPayer])
+        .rpc();
+      assert.fail("Withdrawing into the rent-exempt minimum did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidAmount");
+    }
+
+    // A non-admin cannot withdraw
+    try {
+      await program.methods
+        .withdrawProtocolFees(new BN(1))
+        .accountsPartial({
+          admin: lender.publicKey,
+          config: configPDA,
+          protocolVault: protocolVaultPDA,
+          treasury: admin,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([lender])
+        .rpc();
+      assert.fail("Withdrawing as non-admin did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotAdmin");
+    }
+
+    if (available > 0) {
+      await program.methods
+        .withdrawProtocolFees(new BN(available))
+        .accountsPartial({
+          admin,
+          config: configPDA,
+          protocolVault: protocolVaultPDA,
+          treasury: admin,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([adminPayer])
+        .rpc();
+
+      const vaultBalanceAfter = await provider.connection.getBalance(protocolVaultPDA);
+      expect(vaultBalanceAfter).to.equal(rentExempt);
+    }
+  });
+
+  // Test 11c: Withdraw protocol USDC fees
+  it("Should only let the admin withdraw USDC fees to the treasury", async() => {
+    const fees = Number((await provider.connection.getTokenAccountBalance(protocolVaultUsdcPDA)).value.amount);
+    expect(fees).to.be.greaterThan(0);
+    const treasuryUsdcAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      usdcMint,
+      admin
+    ).then(acc => acc.address);
+    const withdrawUsdc = (signer: Keypair, treasuryAccount: PublicKey, amount: number) => program.methods
+      .withdrawProtocolFeesUsdc(new BN(amount))
+      .accountsPartial({
+        admin: signer.publicKey,
+        config: configPDA,
+        protocolVaultUsdc: protocolVaultUsdcPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        treasuryUsdcAccount: treasuryAccount,
+        tokenProgram: TOKEN_PROGRAM_ID
+      })
+      .signers([signer])
+      .rpc();
+
+    // A non-admin cannot withdraw
+    try {
+      await withdrawUsdc(lender, treasuryUsdcAccount, fees);
+      assert.fail("Withdrawing USDC fees as non-admin did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("NotAdmin");
+    }
+
+    // The admin can only withdraw to a USDC account owned by the treasury
+    try {
+      await withdrawUsdc(adminPayer, lenderUsdcATA, fees);
+      assert.fail("Withdrawing USDC fees outside the treasury did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidTreasury");
+    }
+
+    // Withdrawing more than the vault holds must fail
+    try {
+      await withdrawUsdc(adminPayer, treasuryUsdcAccount, fees + 1);
+      assert.fail("Withdrawing more than the USDC fees did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidAmount");
+    }
+
+    const treasuryBefore = await provider.connection.getTokenAccountBalance(treasuryUsdcAccount);
+    await withdrawUsdc(adminPayer, treasuryUsdcAccount, fees);
+    const treasuryAfter = await provider.connection.getTokenAccountBalance(treasuryUsdcAccount);
+    expect(Number(treasuryAfter.value.amount) - Number(treasuryBefore.value.amount)).to.equal(fees);
+    const vaultAfter = await provider.connection.getTokenAccountBalance(protocolVaultUsdcPDA);
+    expect(vaultAfter.value.amount).to.equal("0");
+  });
+
   // Test 12: Cancel Loan Request
   it("Should cancel a loan request", async() => {
     // Create a loan request to cancel
@@ -770,13 +2916,18 @@ describe("KINLEND PROTOCOL", () => {
           loanAmount,
           collateral,
           noOfDays,
-          new BN(SOL_PRICE) // Pass SOL price directly
+          new BN(0), // registry page index
+          null, // flat lender fee, no APR
+          null // open to any lender
         )
         .accountsStrict({
           borrower: borrower.publicKey,
           loanRequest: cancelLoanRequestPDA,
           collateralVault: cancelCollateralVaultPDA,
           loanRegistry: loanRegistryPDA,
+          loanRegistryPage: loanRegistryPagePDA,
+          config: configPDA,
+          priceUpdate: solPriceUpdate,
           systemProgram: SYSTEM_PROGRAM_ID
         })
         .signers([borrower])
@@ -788,8 +2939,11 @@ describe("KINLEND PROTOCOL", () => {
       // Cancel the loan request
       await program.methods
         .cancelLoanRequest(cancelLoanId)
-        .accounts({
-          borrower: borrower.publicKey
+        .accountsPartial({
+          collateralTokenVault: null, // SOL-backed loan
+          trancheEscrow: null, // no tranches to refund
+          borrower: borrower.publicKey,
+          loanRegistryPage: loanRegistryPagePDA
         })
         .signers([borrower])
         .rpc();
@@ -816,6 +2970,326 @@ describe("KINLEND PROTOCOL", () => {
   });
 
 
+  // Test 12b: SPL-token collateral
+  it("Should create and cancel a loan backed by a whitelisted SPL collateral mint", async() => {
+    const tokenLoanId = new BN(200);
+    const tokenCollateral = new BN(10_000_000); // 0.01 token at the SOL price, well above 150% of $1
+
+    // A liquid-staking style mint priced on the SOL/USD feed
+    const collateralMint = await createMint(provider.connection, adminPayer, admin, null, 9);
+    const feedId = Array.from(Buffer.from(SOL_USD_FEED_ID.slice(2), "hex"));
+
+    const [collateralMintConfigPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_mint"), collateralMint.toBuffer()],
+      program.programId
+    );
+    const [protocolCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("protocol_vault_token"), collateralMint.toBuffer()],
+      program.programId
+    );
+    const [tokenLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), tokenLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [tokenCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), tokenLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+    const [collateralTokenVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_token_vault"), tokenLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    // Whitelist the mint with a 5% haircut
+    await program.methods
+      .whitelistCollateralMint(feedId, 500)
+      .accountsPartial({
+        admin,
+        config: configPDA,
+        collateralMint,
+        collateralMintConfig: collateralMintConfigPDA,
+        protocolCollateralVault: protocolCollateralVaultPDA,
+        protocolVaultAuthority: protocolVaultUsdcAuthorityPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([adminPayer])
+      .rpc();
+
+    const mintConfig = await program.account.collateralMintState.fetch(collateralMintConfigPDA);
+    expect(mintConfig.haircutBps).to.equal(500);
+    expect(mintConfig.decimals).to.equal(9);
+    expect(mintConfig.enabled).to.equal(true);
+
+    const borrowerCollateralAccount = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      adminPayer,
+      collateralMint,
+      borrower.publicKey
+    ).then(acc => acc.address);
+    await mintTo(provider.connection, adminPayer, collateralMint, borrowerCollateralAccount, admin, tokenCollateral.toNumber());
+
+    await program.methods
+      .createLoanRequestToken(tokenLoanId, new BN(1_000_000), tokenCollateral, new BN(7), new BN(0), null, null)
+      .accountsStrict({
+        borrower: borrower.publicKey,
+        loanRequest: tokenLoanRequestPDA,
+        collateralVault: tokenCollateralVaultPDA,
+        collateralTokenVault: collateralTokenVaultPDA,
+        collateralMint,
+        collateralMintConfig: collateralMintConfigPDA,
+        borrowerCollateralAccount,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(tokenLoanRequestPDA);
+    expect(loanRequestAccount.collateralMint.toBase58()).to.equal(collateralMint.toBase58());
+    expect(loanRequestAccount.collateralHaircutBps).to.equal(500);
+    const vaultBalance = await provider.connection.getTokenAccountBalance(collateralTokenVaultPDA);
+    expect(vaultBalance.value.amount).to.equal(tokenCollateral.toString());
+
+    // Cancelling returns the tokens and closes the token vault
+    await program.methods
+      .cancelLoanRequest(tokenLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: tokenLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: collateralTokenVaultPDA,
+        trancheEscrow: null, // no tranches to refund
+        borrowerCollateralAccount,
+        tokenProgram: TOKEN_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const borrowerBalance = await provider.connection.getTokenAccountBalance(borrowerCollateralAccount);
+    expect(borrowerBalance.value.amount).to.equal(tokenCollateral.toString());
+    expect(await provider.connection.getAccountInfo(collateralTokenVaultPDA)).to.equal(null);
+  });
+
+
+  // Test 12c: Add collateral
+  it("Should let the borrower top up the collateral of an open loan", async() => {
+    const topUpLoanId = new BN(101);
+    const initialCollateral = new BN(10_000_000); // 0.01 SOL
+    const extraCollateral = new BN(5_000_000); // 0.005 SOL
+
+    const [topUpLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), topUpLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [topUpCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), topUpLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createLoanRequest(topUpLoanId, new BN(1_000_000), initialCollateral, new BN(7), new BN(0), null, null)
+      .accountsStrict({
+        borrower: borrower.publicKey,
+        loanRequest: topUpLoanRequestPDA,
+        collateralVault: topUpCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const vaultBalanceBefore = await provider.connection.getBalance(topUpCollateralVaultPDA);
+
+    await program.methods
+      .addCollateral(topUpLoanId, extraCollateral)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: topUpLoanRequestPDA,
+        collateralVault: topUpCollateralVaultPDA,
+        collateralTokenVault: null, // SOL-backed loan
+        borrowerCollateralAccount: null,
+        tokenProgram: null,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const loanRequestAccount = await program.account.loanRequestState.fetch(topUpLoanRequestPDA);
+    expect(loanRequestAccount.collateral.toString()).to.equal(initialCollateral.add(extraCollateral).toString());
+    const vaultBalanceAfter = await provider.connection.getBalance(topUpCollateralVaultPDA);
+    expect(vaultBalanceAfter - vaultBalanceBefore).to.equal(extraCollateral.toNumber());
+
+    // A zero top-up is rejected
+    try {
+      await program.methods
+        .addCollateral(topUpLoanId, new BN(0))
+        .accountsPartial({
+          borrower: borrower.publicKey,
+          loanRequest: topUpLoanRequestPDA,
+          collateralVault: topUpCollateralVaultPDA,
+          collateralTokenVault: null,
+          borrowerCollateralAccount: null,
+          tokenProgram: null,
+          systemProgram: SYSTEM_PROGRAM_ID
+        })
+        .signers([borrower])
+        .rpc();
+      assert.fail("Adding zero collateral did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidAmount");
+    }
+
+    // Clean up so the registry page keeps its free slots
+    await program.methods
+      .cancelLoanRequest(topUpLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: topUpLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        trancheEscrow: null, // no tranches to refund
+      })
+      .signers([borrower])
+      .rpc();
+  });
+
+
+  // Test 12d: Withdraw excess collateral
+  it("Should let the borrower withdraw collateral down to the initial ratio only", async() => {
+    const excessLoanId = new BN(102);
+    const initialCollateral = new BN(20_000_000); // 0.02 SOL = $4, 400% of a $1 loan
+
+    const [excessLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), excessLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [excessCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), excessLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .createLoanRequest(excessLoanId, new BN(1_000_000), initialCollateral, new BN(7), new BN(0), null, null)
+      .accountsStrict({
+        borrower: borrower.publicKey,
+        loanRequest: excessLoanRequestPDA,
+        collateralVault: excessCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    const withdraw = (amount: BN) => program.methods
+      .withdrawExcessCollateral(excessLoanId, amount)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: excessLoanRequestPDA,
+        collateralVault: excessCollateralVaultPDA,
+        priceUpdate: solPriceUpdate,
+        collateralMintConfig: null, // SOL-backed loan
+        collateralTokenVault: null,
+        borrowerCollateralAccount: null,
+        tokenProgram: null,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    // 0.01 SOL left is still above the 0.0075 SOL required at 150%
+    await withdraw(new BN(10_000_000));
+    const loanRequestAccount = await program.account.loanRequestState.fetch(excessLoanRequestPDA);
+    expect(loanRequestAccount.collateral.toNumber()).to.equal(10_000_000);
+
+    // Going below 150% is refused
+    try {
+      await withdraw(new BN(5_000_000));
+      assert.fail("Withdrawing below the collateral ratio did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InsuffientCollateral");
+    }
+
+    await program.methods
+      .cancelLoanRequest(excessLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: excessLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        trancheEscrow: null, // no tranches to refund
+      })
+      .signers([borrower])
+      .rpc();
+  });
+
+
+  // Test 12e: APR loans
+  it("Should record an optional APR and reject one out of bounds", async() => {
+    const aprLoanId = new BN(103);
+
+    const [aprLoanRequestPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("loan_request"), borrower.publicKey.toBuffer(), aprLoanId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [aprCollateralVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("collateral_vault"), aprLoanRequestPDA.toBuffer()],
+      program.programId
+    );
+
+    const createWithApr = (aprBps: number) => program.methods
+      .createLoanRequest(aprLoanId, new BN(1_000_000), new BN(7_500_000), new BN(365), new BN(0), aprBps, null)
+      .accountsStrict({
+        borrower: borrower.publicKey,
+        loanRequest: aprLoanRequestPDA,
+        collateralVault: aprCollateralVaultPDA,
+        loanRegistry: loanRegistryPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        config: configPDA,
+        priceUpdate: solPriceUpdate,
+        systemProgram: SYSTEM_PROGRAM_ID
+      })
+      .signers([borrower])
+      .rpc();
+
+    // More than 100% APR is refused
+    try {
+      await createWithApr(10_001);
+      assert.fail("Creating a loan with an APR out of bounds did not fail as expected");
+    } catch(err) {
+      expect(err.toString()).to.include("InvalidInterestRate");
+    }
+
+    // 12% APR accruing per second once funded
+    await createWithApr(1_200);
+    const loanRequestAccount = await program.account.loanRequestState.fetch(aprLoanRequestPDA);
+    expect(loanRequestAccount.aprBps).to.equal(1_200);
+    expect(loanRequestAccount.outstandingPrincipal.toNumber()).to.equal(1_000_000);
+
+    await program.methods
+      .cancelLoanRequest(aprLoanId)
+      .accountsPartial({
+        borrower: borrower.publicKey,
+        loanRequest: aprLoanRequestPDA,
+        loanRegistryPage: loanRegistryPagePDA,
+        collateralTokenVault: null, // SOL-backed loan
+        trancheEscrow: null, // no tranches to refund
+      })
+      .signers([borrower])
+      .rpc();
+  });
+
+
    // Test 4: Update Config
   it("Should update config's usdcMint field", async() => {
      try {
@@ -830,7 +3304,7 @@ describe("KINLEND PROTOCOL", () => {
 
        // Update config to use the new USDC mint
        await program.methods
-         .updateConfig()
+         .updateConfig(null, null, null)
          .accountsPartial({
            admin,
            config: configPDA,